@@ -0,0 +1,384 @@
+//! Retained size analysis of a WASM module, like `twiggy dominators`.
+//!
+//! Functions and data segments form a graph, with the exports, the start
+//! function, the function table and the data segments reachable from a root.
+//! An item retains everything it dominates, which is the code that would be
+//! removed together with it.
+
+use serde::Serialize;
+use std::{cmp::Reverse, collections::HashMap};
+use wasmparser::{ElementItems, ExternalKind, KnownCustom, Name, Operator, Parser, Payload};
+
+#[derive(Debug, Serialize)]
+pub struct Analysis {
+    /// Size of the whole module in bytes.
+    pub size: u64,
+    pub functions: Vec<Item>,
+    pub data: Vec<Item>,
+    pub crates: Vec<Item>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Item {
+    pub name: String,
+    pub shallow_size: u64,
+    pub retained_size: u64,
+}
+
+#[derive(Debug, Default)]
+struct Module {
+    /// Function names by function index, imports included.
+    func_names: HashMap<u32, String>,
+    data_names: HashMap<u32, String>,
+    imported_funcs: u32,
+    /// Code size and callees of the defined functions.
+    bodies: Vec<(u64, Vec<u32>)>,
+    data_sizes: Vec<u64>,
+    /// Functions reachable from outside of the code.
+    roots: Vec<u32>,
+}
+
+impl Module {
+    fn parse(wasm: &[u8]) -> color_eyre::Result<Self> {
+        let mut module = Self::default();
+        for payload in Parser::new(0).parse_all(wasm) {
+            match payload? {
+                Payload::ImportSection(reader) => {
+                    for import in reader {
+                        if let wasmparser::TypeRef::Func(_) = import?.ty {
+                            module.imported_funcs += 1;
+                        }
+                    }
+                }
+                Payload::ExportSection(reader) => {
+                    for export in reader {
+                        let export = export?;
+                        if export.kind == ExternalKind::Func {
+                            module.roots.push(export.index);
+                        }
+                    }
+                }
+                Payload::StartSection { func, .. } => module.roots.push(func),
+                Payload::ElementSection(reader) => {
+                    for element in reader {
+                        if let ElementItems::Functions(funcs) = element?.items {
+                            for func in funcs {
+                                module.roots.push(func?);
+                            }
+                        }
+                    }
+                }
+                Payload::DataSection(reader) => {
+                    for data in reader {
+                        module.data_sizes.push(data?.data.len() as u64);
+                    }
+                }
+                Payload::CodeSectionEntry(body) => {
+                    let mut callees = vec![];
+                    let mut reader = body.get_operators_reader()?;
+                    while !reader.eof() {
+                        match reader.read()? {
+                            Operator::Call { function_index }
+                            | Operator::ReturnCall { function_index }
+                            | Operator::RefFunc { function_index } => callees.push(function_index),
+                            _ => {}
+                        }
+                    }
+                    module.bodies.push((body.range().len() as u64, callees));
+                }
+                Payload::CustomSection(reader) => {
+                    let KnownCustom::Name(reader) = reader.as_known() else {
+                        continue;
+                    };
+                    for name in reader {
+                        let (names, map) = match name? {
+                            Name::Function(map) => (&mut module.func_names, map),
+                            Name::Data(map) => (&mut module.data_names, map),
+                            _ => continue,
+                        };
+                        for naming in map {
+                            let naming = naming?;
+                            names.insert(naming.index, naming.name.to_string());
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(module)
+    }
+
+    /// Node 0 is the root, followed by the defined functions and the data segments.
+    fn graph(&self) -> Vec<Vec<usize>> {
+        let func_node = |index: u32| {
+            index
+                .checked_sub(self.imported_funcs)
+                .map(|index| index as usize + 1)
+                .filter(|node| *node <= self.bodies.len())
+        };
+
+        let mut graph = vec![vec![]];
+        let data_nodes = self.bodies.len() + 1..self.bodies.len() + 1 + self.data_sizes.len();
+        graph[0].extend(self.roots.iter().filter_map(|func| func_node(*func)));
+        graph[0].extend(data_nodes);
+        for (_, callees) in &self.bodies {
+            graph.push(callees.iter().filter_map(|func| func_node(*func)).collect());
+        }
+        graph.resize(graph.len() + self.data_sizes.len(), vec![]);
+        graph
+    }
+}
+
+pub fn analyze(wasm: &[u8]) -> color_eyre::Result<Analysis> {
+    let module = Module::parse(wasm)?;
+    let mut graph = module.graph();
+
+    // Dead code is still in the file, so it is attributed to the root.
+    let (order, _) = reverse_postorder(&graph);
+    let mut reached = vec![false; graph.len()];
+    for node in order {
+        reached[node] = true;
+    }
+    let unreached = (1..graph.len())
+        .filter(|node| !reached[*node])
+        .collect::<Vec<_>>();
+    graph[0].extend(unreached);
+
+    let shallow_sizes = std::iter::once(0)
+        .chain(module.bodies.iter().map(|(size, _)| *size))
+        .chain(module.data_sizes.iter().copied())
+        .collect::<Vec<_>>();
+    let (idom, retained_sizes) = retained_sizes(&graph, &shallow_sizes);
+
+    let func_names = (0..module.bodies.len())
+        .map(|index| {
+            let index = index as u32 + module.imported_funcs;
+            module
+                .func_names
+                .get(&index)
+                .cloned()
+                .unwrap_or_else(|| format!("func[{index}]"))
+        })
+        .collect::<Vec<_>>();
+    let item = |node: usize, name: String| Item {
+        name,
+        shallow_size: shallow_sizes[node],
+        retained_size: retained_sizes[node],
+    };
+
+    let mut functions = vec![];
+    for (index, name) in func_names.iter().enumerate() {
+        functions.push(item(index + 1, name.clone()));
+    }
+    let mut data = vec![];
+    for index in 0..module.data_sizes.len() {
+        let name = module
+            .data_names
+            .get(&(index as u32))
+            .cloned()
+            .unwrap_or_else(|| format!("data[{index}]"));
+        data.push(item(module.bodies.len() + 1 + index, name));
+    }
+
+    let node_crates = std::iter::once(None)
+        .chain(func_names.iter().map(|name| Some(crate_name(name))))
+        .chain(std::iter::repeat_n(None, module.data_sizes.len()))
+        .collect::<Vec<_>>();
+    let crates = crate_sizes(&idom, &node_crates, &shallow_sizes, &retained_sizes);
+
+    functions.sort_by_key(|item| Reverse(item.retained_size));
+    data.sort_by_key(|item| Reverse(item.retained_size));
+    Ok(Analysis {
+        size: wasm.len() as u64,
+        functions,
+        data,
+        crates,
+    })
+}
+
+/// Nodes reachable from node 0 in reverse postorder, and the position of each node in it.
+fn reverse_postorder(graph: &[Vec<usize>]) -> (Vec<usize>, Vec<Option<usize>>) {
+    let mut visited = vec![false; graph.len()];
+    let mut postorder = vec![];
+    let mut stack = vec![(0, 0)];
+    visited[0] = true;
+    while let Some((node, next)) = stack.last_mut() {
+        if let Some(&succ) = graph[*node].get(*next) {
+            *next += 1;
+            if !visited[succ] {
+                visited[succ] = true;
+                stack.push((succ, 0));
+            }
+        } else {
+            postorder.push(*node);
+            stack.pop();
+        }
+    }
+    postorder.reverse();
+
+    let mut position = vec![None; graph.len()];
+    for (index, node) in postorder.iter().enumerate() {
+        position[*node] = Some(index);
+    }
+    (postorder, position)
+}
+
+/// Immediate dominators with the iterative algorithm of Cooper, Harvey and Kennedy.
+/// Every node must be reachable from node 0, which dominates itself.
+fn dominators(graph: &[Vec<usize>]) -> Vec<usize> {
+    let (order, position) = reverse_postorder(graph);
+    let mut preds = vec![vec![]; graph.len()];
+    for (node, succs) in graph.iter().enumerate() {
+        for succ in succs {
+            preds[*succ].push(node);
+        }
+    }
+
+    let mut idom = vec![None; graph.len()];
+    idom[0] = Some(0);
+    let intersect = |idom: &[Option<usize>], mut a: usize, mut b: usize| {
+        while a != b {
+            while position[a] > position[b] {
+                a = idom[a].unwrap();
+            }
+            while position[b] > position[a] {
+                b = idom[b].unwrap();
+            }
+        }
+        a
+    };
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &node in order.iter().skip(1) {
+            let mut new_idom = None;
+            for &pred in &preds[node] {
+                if idom[pred].is_none() {
+                    continue;
+                }
+                new_idom = Some(match new_idom {
+                    Some(new_idom) => intersect(&idom, pred, new_idom),
+                    None => pred,
+                });
+            }
+            if new_idom.is_some() && idom[node] != new_idom {
+                idom[node] = new_idom;
+                changed = true;
+            }
+        }
+    }
+    idom.into_iter().map(|idom| idom.unwrap_or(0)).collect()
+}
+
+fn retained_sizes(graph: &[Vec<usize>], shallow_sizes: &[u64]) -> (Vec<usize>, Vec<u64>) {
+    let idom = dominators(graph);
+    let (order, _) = reverse_postorder(graph);
+    let mut retained_sizes = shallow_sizes.to_vec();
+    // A node comes after its immediate dominator in reverse postorder.
+    for &node in order.iter().skip(1).rev() {
+        retained_sizes[idom[node]] += retained_sizes[node];
+    }
+    (idom, retained_sizes)
+}
+
+/// A crate retains the items dominated by its functions, without counting
+/// a function twice when it is dominated by another function of the same crate.
+fn crate_sizes(
+    idom: &[usize],
+    node_crates: &[Option<&str>],
+    shallow_sizes: &[u64],
+    retained_sizes: &[u64],
+) -> Vec<Item> {
+    let mut children = vec![vec![]; idom.len()];
+    for (node, idom) in idom.iter().enumerate().skip(1) {
+        children[*idom].push(node);
+    }
+
+    let mut crates = HashMap::<&str, Item>::new();
+    let mut path_counts = HashMap::<&str, usize>::new();
+    let mut stack = vec![(0, false)];
+    while let Some((node, exit)) = stack.pop() {
+        let crate_name = node_crates[node];
+        if exit {
+            if let Some(crate_name) = crate_name {
+                *path_counts.get_mut(crate_name).unwrap() -= 1;
+            }
+            continue;
+        }
+
+        if let Some(crate_name) = crate_name {
+            let item = crates.entry(crate_name).or_insert_with(|| Item {
+                name: crate_name.to_string(),
+                shallow_size: 0,
+                retained_size: 0,
+            });
+            item.shallow_size += shallow_sizes[node];
+            let count = path_counts.entry(crate_name).or_default();
+            if *count == 0 {
+                item.retained_size += retained_sizes[node];
+            }
+            *count += 1;
+        }
+        stack.push((node, true));
+        stack.extend(children[node].iter().map(|child| (*child, false)));
+    }
+
+    let mut crates = crates.into_values().collect::<Vec<_>>();
+    crates.sort_by_key(|item| Reverse(item.retained_size));
+    crates
+}
+
+/// The crate of a demangled Rust symbol, e.g. `core` for `<&T as core::fmt::Debug>::fmt`.
+fn crate_name(name: &str) -> &str {
+    let name = name.trim_start_matches('<');
+    let name = match name.split_once(" as ") {
+        Some((ty, trait_name)) if !ty.contains("::") => trait_name.trim_start_matches('<'),
+        _ => name,
+    };
+    match name.split_once("::") {
+        Some((crate_name, _))
+            if !crate_name.is_empty()
+                && crate_name
+                    .chars()
+                    .all(|char| char.is_alphanumeric() || char == '_') =>
+        {
+            crate_name
+        }
+        _ => "(unknown)",
+    }
+}
+
+#[test]
+fn test_retained_sizes() {
+    // 0 -> 1 -> 2 -> 4
+    //   -> 3 -> 2
+    //           5 (dead code)
+    let graph = vec![vec![1, 3, 5], vec![2], vec![4], vec![2], vec![], vec![]];
+    let (idom, retained_sizes) = retained_sizes(&graph, &[0, 10, 20, 30, 40, 50]);
+    assert_eq!(idom, vec![0, 0, 0, 0, 2, 0]);
+    assert_eq!(retained_sizes, vec![150, 10, 60, 30, 40, 50]);
+
+    let crates = crate_sizes(
+        &idom,
+        &[None, Some("a"), Some("b"), Some("a"), Some("b"), None],
+        &[0, 10, 20, 30, 40, 50],
+        &retained_sizes,
+    );
+    let crates = crates
+        .iter()
+        .map(|item| (item.name.as_str(), item.shallow_size, item.retained_size))
+        .collect::<Vec<_>>();
+    assert_eq!(crates, vec![("b", 60, 60), ("a", 40, 40)]);
+}
+
+#[test]
+fn test_crate_name() {
+    assert_eq!(crate_name("leptos::mount::mount_to_body::h0123"), "leptos");
+    assert_eq!(crate_name("<&T as core::fmt::Debug>::fmt"), "core");
+    assert_eq!(
+        crate_name("<alloc::vec::Vec<T> as core::ops::drop::Drop>::drop"),
+        "alloc"
+    );
+    assert_eq!(crate_name("__wbindgen_malloc"), "(unknown)");
+}
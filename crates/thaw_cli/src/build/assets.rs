@@ -1,12 +1,13 @@
 use crate::{context::Context, dx, utils::DotEyre};
 use manganis::AssetOptions;
+use serde::Serialize;
 use std::{
     path::{Path, PathBuf},
     str::FromStr,
 };
 use tokio::fs;
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct BundledAsset {
     pub absolute_source_path: PathBuf,
     pub output_path: PathBuf,
@@ -61,3 +62,44 @@ pub fn asset_subset<'a>(
     }
     Some(subset)
 }
+
+/// Returns the URL paths (relative to `root_dir`) of the bundled stylesheets,
+/// or `None` if any of the assets cannot be updated without a page reload.
+pub fn css_update_paths(assets: &[&BundledAsset], root_dir: &Path) -> Option<Vec<String>> {
+    assets
+        .iter()
+        .map(|asset| {
+            if asset.output_path.extension()? != "css" {
+                return None;
+            }
+            let path = asset.output_path.strip_prefix(root_dir).ok()?;
+            let path = path
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            Some(format!("/{path}"))
+        })
+        .collect()
+}
+
+#[test]
+fn test_css_update_paths() {
+    let root_dir = PathBuf::from("dist");
+    let css = BundledAsset {
+        absolute_source_path: PathBuf::from("/app/assets/main.css"),
+        output_path: root_dir.join("assets").join("main-dxh1234.css"),
+        options: AssetOptions::builder().into_asset_options(),
+    };
+    let image = BundledAsset {
+        absolute_source_path: PathBuf::from("/app/assets/logo.png"),
+        output_path: root_dir.join("assets").join("logo-dxh1234.png"),
+        options: AssetOptions::builder().into_asset_options(),
+    };
+
+    assert_eq!(
+        css_update_paths(&[&css], &root_dir),
+        Some(vec!["/assets/main-dxh1234.css".to_string()])
+    );
+    assert_eq!(css_update_paths(&[&css, &image], &root_dir), None);
+}
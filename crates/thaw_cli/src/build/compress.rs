@@ -0,0 +1,114 @@
+use crate::{
+    config::{Compress, CompressAlgorithm},
+    context::Context,
+    logger,
+};
+use flate2::{Compression, write::GzEncoder};
+use rayon::prelude::*;
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+use tokio::task;
+
+const COMPRESSIBLE_EXTENSIONS: [&str; 11] = [
+    "wasm", "js", "mjs", "css", "html", "svg", "json", "xml", "txt", "map", "ico",
+];
+
+/// Writes precompressed siblings of the files in `dir`, served by `ServeDir::precompressed_*`.
+pub async fn compress_dir(context: &Context, dir: &Path) -> color_eyre::Result<()> {
+    let Compress::Enabled {
+        algorithms,
+        threshold,
+    } = &context.config.build.compress
+    else {
+        return Ok(());
+    };
+
+    context
+        .logger
+        .send(logger::Message::Build("Compressing assets".to_string()))
+        .await?;
+
+    let dir = dir.to_path_buf();
+    let algorithms = algorithms.clone();
+    let threshold = *threshold;
+    task::spawn_blocking(move || {
+        let mut files = vec![];
+        collect_files(&dir, threshold, &mut files)?;
+        files
+            .par_iter()
+            .flat_map(|file| {
+                algorithms
+                    .par_iter()
+                    .map(move |algorithm| (file, algorithm))
+            })
+            .try_for_each(|(file, algorithm)| compress_file(file, *algorithm))
+    })
+    .await??;
+    Ok(())
+}
+
+fn collect_files(dir: &Path, threshold: u64, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            collect_files(&path, threshold, files)?;
+        } else if metadata.len() >= threshold && is_compressible(&path) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn is_compressible(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| COMPRESSIBLE_EXTENSIONS.contains(&extension))
+}
+
+fn compress_file(path: &Path, algorithm: CompressAlgorithm) -> io::Result<()> {
+    let content = compress(&fs::read(path)?, algorithm)?;
+    let mut out_path = path.as_os_str().to_owned();
+    out_path.push(".");
+    out_path.push(algorithm.extension());
+    fs::write(out_path, content)
+}
+
+/// Compresses with the same settings as the precompressed files.
+pub fn compress(content: &[u8], algorithm: CompressAlgorithm) -> io::Result<Vec<u8>> {
+    let mut out = vec![];
+    match algorithm {
+        CompressAlgorithm::Br => {
+            let params = brotli::enc::BrotliEncoderParams {
+                quality: 11,
+                ..Default::default()
+            };
+            let mut encoder = brotli::CompressorWriter::with_params(&mut out, 4096, &params);
+            encoder.write_all(content)?;
+            encoder.flush()?;
+        }
+        CompressAlgorithm::Gzip => {
+            let mut encoder = GzEncoder::new(&mut out, Compression::best());
+            encoder.write_all(content)?;
+            encoder.finish()?;
+        }
+        CompressAlgorithm::Zstd => {
+            let mut encoder = zstd::Encoder::new(&mut out, 19)?;
+            encoder.write_all(content)?;
+            encoder.finish()?;
+        }
+    }
+    Ok(out)
+}
+
+#[test]
+fn test_is_compressible() {
+    assert!(is_compressible(Path::new("dist/assets/app_bg.wasm")));
+    assert!(is_compressible(Path::new("dist/index.html")));
+    assert!(!is_compressible(Path::new("dist/assets/logo.png")));
+    assert!(!is_compressible(Path::new("dist/assets/app.js.br")));
+}
@@ -1,12 +1,20 @@
-use crate::{context::Context, logger, plugins::html::BuildHtml};
+use super::cargo_config_args;
+use crate::{context::Context, logger};
 use color_eyre::eyre::eyre;
 use std::{fs, io::Write};
 
-pub fn cargo_build_args(context: &Context) -> Vec<&'static str> {
-    let mut args = vec!["--target=wasm32-unknown-unknown"];
-    if context.cargo_features_contains_key("csr") {
-        args.push("--features=csr");
-    }
+pub fn cargo_build_args(context: &Context) -> Vec<String> {
+    let default_features: &[&str] = if context.cargo_features_contains_key("csr") {
+        &["csr"]
+    } else {
+        &[]
+    };
+    let config = &context.config.build.client;
+    let mut args = vec![
+        format!("--package={}", context.cargo_package_name()),
+        format!("--target={}", config.client_target()),
+    ];
+    args.extend(cargo_config_args(context, config, default_features));
     args
 }
 
@@ -18,7 +26,7 @@ pub async fn build_index_html(context: &Context) -> color_eyre::Result<()> {
         ))
         .await?;
 
-    let html_path = context.current_dir.join("index.html");
+    let html_path = context.package_dir.join("index.html");
     if !context.serve && !fs::exists(&html_path)? {
         return Err(eyre!(
             "No index.html file was found in the root directory. Location: {html_path:?}"
@@ -26,7 +34,7 @@ pub async fn build_index_html(context: &Context) -> color_eyre::Result<()> {
     }
     let mut html = fs::read_to_string(html_path)?;
 
-    html = BuildHtml::transform(context, html).await?;
+    html = context.plugins.transform_index_html(context, html).await?;
 
     let out_dir = &context.out_dir;
 
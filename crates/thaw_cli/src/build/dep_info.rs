@@ -0,0 +1,54 @@
+//! The dep-info files cargo writes next to its artifacts, in Makefile syntax:
+//! `<artifact>: <source> <source> ...`, with spaces in paths escaped as `\ `.
+
+use std::{
+    collections::HashSet,
+    mem,
+    path::{Path, PathBuf},
+};
+
+/// The source files an artifact was built from, or `None` before the first build.
+pub async fn read_dep_info(path: &Path) -> Option<HashSet<PathBuf>> {
+    let content = tokio::fs::read_to_string(path).await.ok()?;
+    Some(parse_dep_info(&content))
+}
+
+fn parse_dep_info(content: &str) -> HashSet<PathBuf> {
+    let mut paths = HashSet::new();
+    for line in content.lines() {
+        let Some((_, deps)) = line.split_once(": ") else {
+            continue;
+        };
+        let mut path = String::new();
+        let mut chars = deps.chars().peekable();
+        while let Some(char) = chars.next() {
+            match char {
+                '\\' if chars.peek() == Some(&' ') => {
+                    path.push(' ');
+                    chars.next();
+                }
+                ' ' => {
+                    if !path.is_empty() {
+                        paths.insert(PathBuf::from(mem::take(&mut path)));
+                    }
+                }
+                char => path.push(char),
+            }
+        }
+        if !path.is_empty() {
+            paths.insert(PathBuf::from(path));
+        }
+    }
+    paths
+}
+
+#[test]
+fn test_parse_dep_info() {
+    let paths = parse_dep_info(
+        "/app/target/debug/server: /app/src/main.rs /app/src/my\\ app.rs /app/src/lib.rs\n",
+    );
+    assert_eq!(paths.len(), 3);
+    assert!(paths.contains(Path::new("/app/src/my app.rs")));
+    assert!(paths.contains(Path::new("/app/src/lib.rs")));
+    assert!(!paths.contains(Path::new("/app/target/debug/server")));
+}
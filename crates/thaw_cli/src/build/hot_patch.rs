@@ -0,0 +1,703 @@
+//! Subsecond-style hot-patching for `thaw serve csr`.
+//!
+//! The base build exports every symbol and its function table. When only Rust
+//! sources change, the app crate is recompiled alone into a position independent
+//! side module that imports everything else from the running base module. The
+//! client instantiates it against the live memory and table, then points the
+//! table slots of the changed functions at the new code, so reactive state
+//! living in memory survives the patch.
+//!
+//! Only functions in the table can be replaced. The functions of each patch are
+//! compared with the previous compilation, and a change to a function that is
+//! only called directly falls back to a full rebuild.
+
+use crate::{context::Context, logger};
+use color_eyre::eyre::{OptionExt, eyre};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    env,
+    ffi::OsString,
+    hash::{DefaultHasher, Hash, Hasher},
+    path::{Path, PathBuf},
+    process,
+};
+use tokio::{fs, process::Command};
+use wasmparser::{
+    Dylink0Subsection, ElementItems, ElementKind, ExternalKind, KnownCustom, Operator, Parser,
+    Payload, TypeRef,
+};
+
+/// Set on the cargo child of a hot-patch base build, see [`run_rustc_wrapper`].
+const RUSTC_CAPTURE_PATH_ENV: &str = "THAW_CLI_HOT_PATCH_RUSTC";
+const CRATE_NAME_ENV: &str = "THAW_CLI_HOT_PATCH_CRATE";
+/// The `RUSTC_WORKSPACE_WRAPPER` set by the user, run by the `thaw` wrapper in turn.
+const USER_WRAPPER_ENV: &str = "THAW_CLI_HOT_PATCH_WRAPPER";
+
+#[inline]
+pub fn is_enabled(context: &Context) -> bool {
+    context.serve && !context.ssr && context.config.server.hot_patch
+}
+
+fn hot_patch_dir(context: &Context) -> PathBuf {
+    context.target_dir.join("thaw-cli").join("hot-patch")
+}
+
+/// Link arguments of the base build, so that patches can import any symbol
+/// and add their functions to the table.
+pub fn base_rustflags() -> Vec<&'static str> {
+    vec![
+        "-Clink-arg=--export-all",
+        "-Clink-arg=--export-table",
+        "-Clink-arg=--growable-table",
+        "-Clink-arg=--export=__stack_pointer",
+    ]
+}
+
+/// Runs the app crate compilation through `thaw` to record its rustc invocation.
+pub fn base_envs(context: &Context) -> color_eyre::Result<Vec<(&'static str, OsString)>> {
+    let user_wrapper = context
+        .env
+        .get("RUSTC_WORKSPACE_WRAPPER")
+        .map(OsString::from)
+        .or_else(|| env::var_os("RUSTC_WORKSPACE_WRAPPER"))
+        .filter(|wrapper| !wrapper.is_empty());
+    let mut envs = vec![
+        ("RUSTC_WORKSPACE_WRAPPER", env::current_exe()?.into()),
+        (
+            RUSTC_CAPTURE_PATH_ENV,
+            hot_patch_dir(context).join("rustc.json").into(),
+        ),
+        (
+            CRATE_NAME_ENV,
+            context.cargo_package_name().replace('-', "_").into(),
+        ),
+    ];
+    if let Some(user_wrapper) = user_wrapper {
+        envs.push((USER_WRAPPER_ENV, user_wrapper));
+    }
+    Ok(envs)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RustcInvocation {
+    /// The user's `RUSTC_WORKSPACE_WRAPPER`, which runs `rustc`.
+    wrapper: Option<String>,
+    rustc: String,
+    args: Vec<String>,
+    /// The whole environment, including the `cargo:rustc-env` values of build scripts.
+    envs: Vec<(String, String)>,
+    current_dir: PathBuf,
+}
+
+impl RustcInvocation {
+    fn command(&self) -> Command {
+        let mut cmd = match &self.wrapper {
+            Some(wrapper) => {
+                let mut cmd = Command::new(wrapper);
+                cmd.arg(&self.rustc);
+                cmd
+            }
+            None => Command::new(&self.rustc),
+        };
+        cmd.env_clear()
+            .envs(self.envs.iter().cloned())
+            .current_dir(&self.current_dir);
+        cmd
+    }
+}
+
+/// When `thaw` is invoked as the `RUSTC_WORKSPACE_WRAPPER` of a hot-patch base build,
+/// records the rustc invocation of the app crate, runs rustc and returns its exit code.
+/// The app crate is then compiled like a patch, as the baseline the patches are compared to.
+pub fn run_rustc_wrapper() -> color_eyre::Result<Option<i32>> {
+    let Some(capture_path) = env::var_os(RUSTC_CAPTURE_PATH_ENV) else {
+        return Ok(None);
+    };
+    let mut args = env::args().skip(1);
+    let Some(rustc) = args.next() else {
+        return Ok(None);
+    };
+    let args = args.collect::<Vec<_>>();
+    let wrapper = env::var(USER_WRAPPER_ENV).ok();
+
+    let capture_path = PathBuf::from(capture_path);
+    let is_app_crate =
+        env::var(CRATE_NAME_ENV).is_ok_and(|crate_name| is_app_crate(&args, &crate_name));
+    if is_app_crate {
+        let invocation = RustcInvocation {
+            wrapper: wrapper.clone(),
+            rustc: rustc.clone(),
+            args: args.clone(),
+            envs: env::vars()
+                .filter(|(key, _)| {
+                    ![RUSTC_CAPTURE_PATH_ENV, CRATE_NAME_ENV, USER_WRAPPER_ENV]
+                        .contains(&key.as_str())
+                })
+                .collect(),
+            current_dir: env::current_dir()?,
+        };
+        if let Some(parent) = capture_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&capture_path, serde_json::to_vec(&invocation)?)?;
+    }
+
+    let rustc_command = || match &wrapper {
+        Some(wrapper) => {
+            let mut cmd = process::Command::new(wrapper);
+            cmd.arg(&rustc);
+            cmd
+        }
+        None => process::Command::new(&rustc),
+    };
+    let status = rustc_command().args(&args).status()?;
+    if status.success()
+        && is_app_crate
+        && let Some(dir) = capture_path.parent()
+    {
+        let obj_dir = dir.join("baseline");
+        if obj_dir.exists() {
+            std::fs::remove_dir_all(&obj_dir)?;
+        }
+        std::fs::create_dir_all(&obj_dir)?;
+        // The diagnostics were already reported by the build, a failure only disables
+        // hot patching.
+        rustc_command()
+            .args(patch_rustc_args(&args, &obj_dir, &dir.join("incremental")))
+            .stdout(process::Stdio::null())
+            .stderr(process::Stdio::null())
+            .status()?;
+    }
+    Ok(Some(status.code().unwrap_or(1)))
+}
+
+fn is_app_crate(args: &[String], crate_name: &str) -> bool {
+    let has_arg = |name: &str, is_value: &dyn Fn(&str) -> bool| {
+        args.windows(2)
+            .any(|pair| pair[0] == name && is_value(&pair[1]))
+    };
+    has_arg("--crate-name", &|name| name == crate_name)
+        && has_arg("--crate-type", &|ty| ty == "bin" || ty == "cdylib")
+}
+
+/// Rewrites the recorded app crate invocation to only emit relocatable objects.
+fn patch_rustc_args(args: &[String], obj_dir: &Path, incremental_dir: &Path) -> Vec<String> {
+    let mut patch_args = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--emit" | "--out-dir" => {
+                args.next();
+            }
+            "-C" => match args.next() {
+                Some(value) if value.starts_with("incremental=") => {}
+                Some(value) => {
+                    patch_args.push(arg.clone());
+                    patch_args.push(value.clone());
+                }
+                None => {}
+            },
+            arg if arg.starts_with("--emit=") || arg.starts_with("-Cincremental=") => {}
+            _ => patch_args.push(arg.clone()),
+        }
+    }
+    patch_args.extend([
+        "--emit=obj".to_string(),
+        "--out-dir".to_string(),
+        obj_dir.display().to_string(),
+        format!("-Cincremental={}", incremental_dir.display()),
+        "-Crelocation-model=pic".to_string(),
+    ]);
+    patch_args
+}
+
+/// Sent to the client, which loads `lib` as a side module of the running app.
+#[derive(Debug, Clone, Serialize)]
+pub struct HotPatch {
+    /// URL path of the patch module.
+    pub lib: String,
+    pub memory_export: String,
+    pub table_export: String,
+    pub memory_size: u32,
+    /// Power of 2.
+    pub memory_alignment: u32,
+    pub table_size: u32,
+    /// Power of 2.
+    pub table_alignment: u32,
+    /// Table slots of the base module and the patch export replacing them.
+    pub table: BTreeMap<u32, String>,
+}
+
+#[derive(Debug)]
+pub struct HotPatcher {
+    invocation: RustcInvocation,
+    rust_lld: PathBuf,
+    base: BaseModule,
+    /// Function hashes of the last compilation of the app crate.
+    functions: HashMap<String, u64>,
+    patch_count: usize,
+}
+
+impl HotPatcher {
+    pub async fn new(context: &Context) -> color_eyre::Result<Self> {
+        let capture_path = hot_patch_dir(context).join("rustc.json");
+        let invocation = fs::read(&capture_path)
+            .await
+            .map_err(|_| eyre!("The app crate invocation was not recorded, run a clean build"))?;
+        let invocation = serde_json::from_slice(&invocation)?;
+
+        let wasm_path = context
+            .assets_dir
+            .join(format!("{}_bg.wasm", context.output_name()?));
+        let base = BaseModule::parse(&fs::read(wasm_path).await?)?;
+
+        let rust_lld = rust_lld_path().await?;
+        // Patches are compared with the unchanged sources to find the changed functions.
+        let dir = hot_patch_dir(context);
+        let baseline = link_patch(&rust_lld, &dir.join("baseline"), &dir.join("baseline.wasm"))
+            .await
+            .map_err(|_| eyre!("The app crate baseline was not compiled, run a clean build"))?;
+
+        Ok(Self {
+            invocation,
+            rust_lld,
+            base,
+            functions: baseline.functions,
+            patch_count: 0,
+        })
+    }
+
+    /// Recompiles the app crate into a patch module in `assets_dir`.
+    pub async fn patch(&mut self, context: &Context) -> color_eyre::Result<HotPatch> {
+        context
+            .logger
+            .send(logger::Message::Build("Building hot patch".to_string()))
+            .await?;
+
+        self.patch_count += 1;
+        let lib_name = format!("__thaw_cli_patch_{}.wasm", self.patch_count);
+        let patch = self
+            .compile(context, &context.assets_dir.join(&lib_name))
+            .await?;
+        let hot_patch = self.base.jump_table(
+            &patch,
+            &self.functions,
+            format!("/{}/{lib_name}", context.config.build.assets_dir),
+        )?;
+        self.functions = patch.functions;
+        Ok(hot_patch)
+    }
+
+    /// Compiles the app crate into a side module at `lib_path`.
+    async fn compile(&self, context: &Context, lib_path: &Path) -> color_eyre::Result<PatchModule> {
+        let dir = hot_patch_dir(context);
+        let obj_dir = dir.join("obj");
+        if fs::try_exists(&obj_dir).await? {
+            fs::remove_dir_all(&obj_dir).await?;
+        }
+        fs::create_dir_all(&obj_dir).await?;
+
+        let output = self
+            .invocation
+            .command()
+            .args(patch_rustc_args(
+                &self.invocation.args,
+                &obj_dir,
+                &dir.join("incremental"),
+            ))
+            .kill_on_drop(true)
+            .output()
+            .await?;
+        if !output.status.success() {
+            return Err(eyre!("Failed to compile the app crate"));
+        }
+
+        link_patch(&self.rust_lld, &obj_dir, lib_path).await
+    }
+}
+
+/// Links the objects in `obj_dir` into a side module at `lib_path`.
+async fn link_patch(
+    rust_lld: &Path,
+    obj_dir: &Path,
+    lib_path: &Path,
+) -> color_eyre::Result<PatchModule> {
+    let mut objects = vec![];
+    let mut entries = fs::read_dir(obj_dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        if entry.path().extension().is_some_and(|ext| ext == "o") {
+            objects.push(entry.path());
+        }
+    }
+
+    let output = Command::new(rust_lld)
+        .args([
+            "-flavor",
+            "wasm",
+            "--experimental-pic",
+            "-shared",
+            "--import-memory",
+            "--import-table",
+            "--allow-undefined",
+            "--export-all",
+            "--no-entry",
+            "-o",
+        ])
+        .arg(lib_path)
+        .args(objects)
+        .kill_on_drop(true)
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(eyre!(
+            "Failed to link the hot patch: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    PatchModule::parse(&fs::read(lib_path).await?)
+}
+
+async fn rust_lld_path() -> color_eyre::Result<PathBuf> {
+    let sysroot = Command::new("rustc")
+        .args(["--print", "sysroot"])
+        .output()
+        .await?;
+    let version = Command::new("rustc").arg("-vV").output().await?;
+    let host = String::from_utf8(version.stdout)?
+        .lines()
+        .find_map(|line| line.strip_prefix("host: ").map(ToString::to_string))
+        .ok_or_eyre("Failed to get the rustc host")?;
+
+    let mut path = PathBuf::from(String::from_utf8(sysroot.stdout)?.trim())
+        .join("lib/rustlib")
+        .join(host)
+        .join("bin/rust-lld");
+    if cfg!(windows) {
+        path.set_extension("exe");
+    }
+    Ok(path)
+}
+
+/// The served app module, after wasm-bindgen.
+#[derive(Debug, Default)]
+struct BaseModule {
+    memory_export: Option<String>,
+    table_export: Option<String>,
+    exports: HashSet<String>,
+    /// Table slots and the export names of the functions in them.
+    table: Vec<(u32, String)>,
+}
+
+impl BaseModule {
+    fn parse(wasm: &[u8]) -> color_eyre::Result<Self> {
+        let mut module = Self::default();
+        let mut func_exports = HashMap::<u32, String>::new();
+        let mut elements = vec![];
+
+        for payload in Parser::new(0).parse_all(wasm) {
+            match payload? {
+                Payload::ExportSection(reader) => {
+                    for export in reader {
+                        let export = export?;
+                        match export.kind {
+                            ExternalKind::Func => {
+                                func_exports.insert(export.index, export.name.to_string());
+                            }
+                            ExternalKind::Memory if export.index == 0 => {
+                                module.memory_export = Some(export.name.to_string());
+                            }
+                            ExternalKind::Table if export.index == 0 => {
+                                module.table_export = Some(export.name.to_string());
+                            }
+                            _ => {}
+                        }
+                        module.exports.insert(export.name.to_string());
+                    }
+                }
+                Payload::ElementSection(reader) => {
+                    for element in reader {
+                        let element = element?;
+                        let ElementKind::Active {
+                            table_index: None | Some(0),
+                            offset_expr,
+                        } = element.kind
+                        else {
+                            continue;
+                        };
+                        let ElementItems::Functions(funcs) = element.items else {
+                            continue;
+                        };
+                        let Operator::I32Const { value: offset } =
+                            offset_expr.get_operators_reader().read()?
+                        else {
+                            continue;
+                        };
+                        for (slot, func) in funcs.into_iter().enumerate() {
+                            elements.push((offset as u32 + slot as u32, func?));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        module.table = elements
+            .into_iter()
+            .filter_map(|(slot, func)| Some((slot, func_exports.get(&func)?.clone())))
+            .collect();
+        Ok(module)
+    }
+
+    /// Maps the functions changed since `previous` to their table slots. Fails when a changed
+    /// function of the base module has no slot, as its direct calls cannot be redirected.
+    fn jump_table(
+        &self,
+        patch: &PatchModule,
+        previous: &HashMap<String, u64>,
+        lib: String,
+    ) -> color_eyre::Result<HotPatch> {
+        if let Some(name) = patch
+            .func_imports
+            .iter()
+            .find(|name| !self.exports.contains(*name))
+        {
+            return Err(eyre!("The hot patch imports the unknown symbol `{name}`"));
+        }
+
+        let changed = patch
+            .functions
+            .iter()
+            .filter(|(name, hash)| previous.get(*name) != Some(*hash))
+            .map(|(name, _)| name)
+            .collect::<HashSet<_>>();
+        let table = self
+            .table
+            .iter()
+            .filter(|(_, name)| changed.contains(name))
+            .map(|(slot, name)| (*slot, name.clone()))
+            .collect::<BTreeMap<_, _>>();
+        // New functions are only reached through the changed functions calling them.
+        if let Some(name) = changed.iter().find(|name| {
+            self.exports.contains(name.as_str()) && !table.values().any(|slot| slot == **name)
+        }) {
+            return Err(eyre!(
+                "`{name}` changed but is called directly, so it cannot be hot patched"
+            ));
+        }
+        if table.is_empty() {
+            return Err(eyre!("The hot patch does not replace any function"));
+        }
+
+        Ok(HotPatch {
+            lib,
+            memory_export: self
+                .memory_export
+                .clone()
+                .ok_or_eyre("The app module does not export its memory")?,
+            table_export: self
+                .table_export
+                .clone()
+                .ok_or_eyre("The app module does not export its function table")?,
+            memory_size: patch.memory_size,
+            memory_alignment: patch.memory_alignment,
+            table_size: patch.table_size,
+            table_alignment: patch.table_alignment,
+            table,
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct PatchModule {
+    memory_size: u32,
+    memory_alignment: u32,
+    table_size: u32,
+    table_alignment: u32,
+    /// Functions imported from the base module.
+    func_imports: Vec<String>,
+    /// Hashes of the exported functions, with calls and imported globals named instead
+    /// of indexed, so the hash only changes with the function itself.
+    functions: HashMap<String, u64>,
+}
+
+impl PatchModule {
+    fn parse(wasm: &[u8]) -> color_eyre::Result<Self> {
+        let mut module = Self::default();
+        let mut global_imports = vec![];
+        let mut func_names = HashMap::<u32, String>::new();
+        let mut func_index = 0;
+        for payload in Parser::new(0).parse_all(wasm) {
+            match payload? {
+                Payload::CustomSection(reader) => {
+                    let KnownCustom::Dylink0(reader) = reader.as_known() else {
+                        continue;
+                    };
+                    for subsection in reader {
+                        if let Dylink0Subsection::MemInfo(info) = subsection? {
+                            module.memory_size = info.memory_size;
+                            module.memory_alignment = info.memory_alignment;
+                            module.table_size = info.table_size;
+                            module.table_alignment = info.table_alignment;
+                        }
+                    }
+                }
+                Payload::ImportSection(reader) => {
+                    for import in reader {
+                        let import = import?;
+                        match import.ty {
+                            TypeRef::Func(_) => {
+                                func_names.insert(func_index, import.name.to_string());
+                                func_index += 1;
+                                if import.module == "env" {
+                                    module.func_imports.push(import.name.to_string());
+                                }
+                            }
+                            TypeRef::Global(_) => global_imports.push(import.name.to_string()),
+                            _ => {}
+                        }
+                    }
+                }
+                Payload::ExportSection(reader) => {
+                    for export in reader {
+                        let export = export?;
+                        if export.kind == ExternalKind::Func {
+                            func_names.insert(export.index, export.name.to_string());
+                        }
+                    }
+                }
+                Payload::CodeSectionEntry(body) => {
+                    let index = func_index;
+                    func_index += 1;
+                    let Some(name) = func_names.get(&index) else {
+                        continue;
+                    };
+                    let func_name = |index: u32| match func_names.get(&index) {
+                        Some(name) => name.clone(),
+                        None => format!("#{index}"),
+                    };
+                    let global_name = |index: u32| match global_imports.get(index as usize) {
+                        Some(name) => name.clone(),
+                        None => format!("#{index}"),
+                    };
+
+                    let mut hasher = DefaultHasher::new();
+                    for local in body.get_locals_reader()? {
+                        format!("{:?}", local?).hash(&mut hasher);
+                    }
+                    let mut reader = body.get_operators_reader()?;
+                    while !reader.eof() {
+                        match reader.read()? {
+                            Operator::Call { function_index }
+                            | Operator::ReturnCall { function_index }
+                            | Operator::RefFunc { function_index } => {
+                                func_name(function_index).hash(&mut hasher)
+                            }
+                            Operator::GlobalGet { global_index }
+                            | Operator::GlobalSet { global_index } => {
+                                global_name(global_index).hash(&mut hasher)
+                            }
+                            operator => format!("{operator:?}").hash(&mut hasher),
+                        }
+                    }
+                    module.functions.insert(name.clone(), hasher.finish());
+                }
+                _ => {}
+            }
+        }
+        Ok(module)
+    }
+}
+
+#[test]
+fn test_patch_rustc_args() {
+    let args = [
+        "--crate-name",
+        "app",
+        "--edition=2024",
+        "src/main.rs",
+        "--crate-type",
+        "bin",
+        "--emit=dep-info,link",
+        "-C",
+        "incremental=/target/debug/incremental",
+        "-C",
+        "debuginfo=2",
+        "--out-dir",
+        "/target/debug/deps",
+    ]
+    .map(String::from);
+
+    assert_eq!(
+        patch_rustc_args(&args, Path::new("/obj"), Path::new("/incremental")),
+        [
+            "--crate-name",
+            "app",
+            "--edition=2024",
+            "src/main.rs",
+            "--crate-type",
+            "bin",
+            "-C",
+            "debuginfo=2",
+            "--emit=obj",
+            "--out-dir",
+            "/obj",
+            "-Cincremental=/incremental",
+            "-Crelocation-model=pic",
+        ]
+    );
+    assert!(is_app_crate(&args, "app"));
+}
+
+#[test]
+fn test_jump_table() {
+    let base = BaseModule {
+        memory_export: Some("memory".to_string()),
+        table_export: Some("__indirect_function_table".to_string()),
+        exports: HashSet::from([
+            "render".to_string(),
+            "alloc".to_string(),
+            "init".to_string(),
+        ]),
+        table: vec![(1, "render".to_string()), (2, "alloc".to_string())],
+    };
+    let previous = HashMap::from([
+        ("render".to_string(), 1),
+        ("alloc".to_string(), 2),
+        ("init".to_string(), 3),
+    ]);
+    let patch = PatchModule {
+        func_imports: vec!["alloc".to_string()],
+        functions: HashMap::from([
+            ("render".to_string(), 4),
+            ("init".to_string(), 3),
+            ("helper".to_string(), 5),
+        ]),
+        ..Default::default()
+    };
+    let hot_patch = base.jump_table(&patch, &previous, "/assets/patch.wasm".to_string());
+    assert_eq!(
+        hot_patch.unwrap().table,
+        BTreeMap::from([(1, "render".to_string())])
+    );
+
+    let patch = PatchModule {
+        functions: HashMap::from([("init".to_string(), 4)]),
+        ..Default::default()
+    };
+    assert!(base.jump_table(&patch, &previous, String::new()).is_err());
+
+    let patch = PatchModule {
+        functions: previous.clone(),
+        ..Default::default()
+    };
+    assert!(base.jump_table(&patch, &previous, String::new()).is_err());
+
+    let patch = PatchModule {
+        func_imports: vec!["missing".to_string()],
+        functions: HashMap::from([("render".to_string(), 4)]),
+        ..Default::default()
+    };
+    assert!(base.jump_table(&patch, &previous, String::new()).is_err());
+}
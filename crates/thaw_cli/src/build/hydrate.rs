@@ -1,7 +1,13 @@
-pub fn cargo_build_args() -> Vec<&'static str> {
-    vec![
-        "--target=wasm32-unknown-unknown",
-        "--lib",
-        "--features=hydrate",
-    ]
+use super::cargo_config_args;
+use crate::context::Context;
+
+pub fn cargo_build_args(context: &Context) -> Vec<String> {
+    let config = &context.config.build.client;
+    let mut args = vec![
+        format!("--package={}", context.cargo_package_name()),
+        format!("--target={}", config.client_target()),
+        "--lib".to_string(),
+    ];
+    args.extend(cargo_config_args(context, config, &["hydrate"]));
+    args
 }
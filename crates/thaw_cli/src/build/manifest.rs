@@ -0,0 +1,119 @@
+use super::{
+    assets::BundledAsset, cargo_build_exe_name, client_assets_dir, content_hash,
+    wasm::main_bundle_names,
+};
+use crate::{context::Context, logger};
+use manganis::AssetOptions;
+use serde::Serialize;
+use std::path::Path;
+use tokio::fs;
+
+/// Written to `out_dir/manifest.json` by `thaw build`.
+#[derive(Debug, Serialize)]
+struct Manifest<'a> {
+    entry: ManifestEntry,
+    assets: Vec<ManifestAsset<'a>>,
+    /// The SSR executable.
+    server: Option<ManifestFile>,
+}
+
+#[derive(Debug, Serialize)]
+struct ManifestEntry {
+    js: ManifestFile,
+    wasm: ManifestFile,
+}
+
+#[derive(Debug, Serialize)]
+struct ManifestAsset<'a> {
+    source: &'a Path,
+    #[serde(flatten)]
+    file: ManifestFile,
+    options: &'a AssetOptions,
+}
+
+#[derive(Debug, Serialize)]
+struct ManifestFile {
+    /// Relative to `out_dir`, with `/` separators.
+    path: String,
+    size: u64,
+    hash: String,
+}
+
+impl ManifestFile {
+    async fn new(path: &Path, out_dir: &Path) -> color_eyre::Result<Self> {
+        let content = fs::read(path).await?;
+        Ok(Self {
+            path: relative_path(path, out_dir),
+            size: content.len() as u64,
+            hash: content_hash(&content),
+        })
+    }
+}
+
+fn relative_path(path: &Path, out_dir: &Path) -> String {
+    path.strip_prefix(out_dir)
+        .unwrap_or(path)
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Describes the emitted artifacts, so that deploy tooling does not need to glob `out_dir`.
+pub async fn write_manifest(context: &Context, assets: &[BundledAsset]) -> color_eyre::Result<()> {
+    context
+        .logger
+        .send(logger::Message::Build(
+            "Writing manifest.json file".to_string(),
+        ))
+        .await?;
+
+    let out_dir = &context.out_dir;
+    let assets_dir = client_assets_dir(context);
+
+    let (js_name, wasm_name) = main_bundle_names(context).await?;
+    let entry = ManifestEntry {
+        js: ManifestFile::new(&assets_dir.join(js_name), out_dir).await?,
+        wasm: ManifestFile::new(&assets_dir.join(wasm_name), out_dir).await?,
+    };
+
+    let mut manifest_assets = vec![];
+    for asset in assets {
+        manifest_assets.push(ManifestAsset {
+            source: &asset.absolute_source_path,
+            file: ManifestFile::new(&asset.output_path, out_dir).await?,
+            options: &asset.options,
+        });
+    }
+
+    let server = if context.ssr {
+        let exe_path = out_dir.join("server").join(cargo_build_exe_name(context)?);
+        Some(ManifestFile::new(&exe_path, out_dir).await?)
+    } else {
+        None
+    };
+
+    let manifest = Manifest {
+        entry,
+        assets: manifest_assets,
+        server,
+    };
+    fs::write(
+        out_dir.join("manifest.json"),
+        serde_json::to_string_pretty(&manifest)?,
+    )
+    .await?;
+    Ok(())
+}
+
+#[test]
+fn test_relative_path() {
+    let out_dir = Path::new("dist");
+    assert_eq!(
+        relative_path(
+            &out_dir.join("client").join("assets").join("app.js"),
+            out_dir
+        ),
+        "client/assets/app.js"
+    );
+}
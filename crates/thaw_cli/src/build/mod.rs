@@ -1,19 +1,31 @@
+pub mod analyze;
 pub mod assets;
+pub mod compress;
 pub mod csr;
+mod dep_info;
+pub mod hot_patch;
 pub mod hydrate;
+mod manifest;
+mod report;
+pub mod ssg;
 pub mod ssr;
-mod wasm;
+pub mod wasm;
 
 pub use assets::collect_assets;
+pub use compress::compress_dir;
+pub use manifest::write_manifest;
+pub use report::report_sizes;
 pub use wasm::wasm_bindgen;
 
 use crate::{
+    config::CargoBuildConfig,
     context::Context,
     logger,
     utils::fs::{clear_dir, copy_dir_all},
 };
-use color_eyre::eyre::eyre;
+use cargo_metadata::diagnostic::{Diagnostic, DiagnosticLevel};
 use std::{
+    fmt,
     path::{Path, PathBuf},
     process::Stdio,
 };
@@ -62,31 +74,146 @@ pub async fn copy_public_dir(context: &Context, out_dir: &Path) -> color_eyre::R
     Ok(())
 }
 
+/// The directory served to the browser, `out_dir/client` for SSR.
+pub fn client_out_dir(context: &Context) -> PathBuf {
+    if context.ssr {
+        context.out_dir.join("client")
+    } else {
+        context.out_dir.clone()
+    }
+}
+
+/// The directory of the main JS/WASM bundle and the collected assets.
+pub fn client_assets_dir(context: &Context) -> PathBuf {
+    client_out_dir(context).join(&context.config.build.assets_dir)
+}
+
+/// Hex encoded hash of a file content, used in file names and the build manifest.
+pub fn content_hash(bytes: &[u8]) -> String {
+    format!("{:016x}", seahash::hash(bytes))
+}
+
 pub fn cargo_build_exe_name(context: &Context) -> color_eyre::Result<String> {
-    let mut exe_name = context.cargo_package_name()?;
+    let mut exe_name = context.server_bin_name();
     if cfg!(windows) {
         exe_name.push_str(".exe");
     }
     Ok(exe_name)
 }
 
+/// Returned by [`run_cargo_build`] when cargo reports a failed build.
+#[derive(Debug)]
+pub struct CargoBuildError {
+    /// Error-level diagnostics emitted by the compiler during the build.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl fmt::Display for CargoBuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Cargo build failed")
+    }
+}
+
+impl std::error::Error for CargoBuildError {}
+
+/// The features, profile and extra arguments of a cargo build, the target is left to the caller.
+pub fn cargo_config_args(
+    context: &Context,
+    config: &CargoBuildConfig,
+    default_features: &[&str],
+) -> Vec<String> {
+    let mut args = vec![];
+    let features = match &config.features {
+        Some(features) => features.join(","),
+        None => default_features.join(","),
+    };
+    if !features.is_empty() {
+        args.push(format!("--features={features}"));
+    }
+    if config.no_default_features {
+        args.push("--no-default-features".to_string());
+    }
+    if context.config.release {
+        match &config.profile {
+            Some(profile) => args.push(format!("--profile={profile}")),
+            None => args.push("--release".to_string()),
+        }
+    }
+    args.extend(config.args.iter().cloned());
+    args
+}
+
+/// Appends `rustflags` to the flags set by the user. Cargo ignores `RUSTFLAGS` when
+/// `CARGO_ENCODED_RUSTFLAGS` is set, so the encoded flags are extended in that case.
+fn append_rustflags(
+    encoded: Option<String>,
+    inherited: Option<String>,
+    rustflags: &[&str],
+) -> (&'static str, String) {
+    match encoded {
+        Some(encoded) => {
+            let flags = rustflags.iter().flat_map(|flags| flags.split_whitespace());
+            let flags = std::iter::once(encoded.as_str()).chain(flags);
+            (
+                "CARGO_ENCODED_RUSTFLAGS",
+                flags.collect::<Vec<_>>().join("\x1f"),
+            )
+        }
+        None => {
+            let flags = inherited
+                .iter()
+                .map(String::as_str)
+                .chain(rustflags.iter().copied());
+            ("RUSTFLAGS", flags.collect::<Vec<_>>().join(" "))
+        }
+    }
+}
+
+/// Runs `cargo build`, returning the last built executable. The output lines are prefixed
+/// with `label` when set, to tell concurrent builds apart.
 pub async fn run_cargo_build(
     context: &Context,
-    args: Vec<&'static str>,
+    args: Vec<String>,
+    config: &CargoBuildConfig,
+    label: Option<&str>,
 ) -> color_eyre::Result<Option<PathBuf>> {
     let mut cmd = Command::new("cargo");
     cmd.envs(context.env.cloned_into_iter());
     cmd.arg("build");
     cmd.args(args);
-    if context.config.release {
-        cmd.arg("--release");
-    }
     cmd.arg("--message-format=json-diagnostic-rendered-ansi");
 
+    let mut rustflags = config
+        .rustflags
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
     if context.serve && context.config.server.erase_components {
-        cmd.env("RUSTFLAGS", "--cfg erase_components");
+        rustflags.push("--cfg erase_components");
+    }
+    if hot_patch::is_enabled(context) {
+        rustflags.extend(hot_patch::base_rustflags());
+        cmd.envs(hot_patch::base_envs(context)?);
+    }
+    if !rustflags.is_empty() {
+        let inherited = |key: &str| {
+            context
+                .env
+                .get(key)
+                .cloned()
+                .or_else(|| std::env::var(key).ok())
+                .filter(|value| !value.is_empty())
+        };
+        let (key, value) = append_rustflags(
+            inherited("CARGO_ENCODED_RUSTFLAGS"),
+            inherited("RUSTFLAGS"),
+            &rustflags,
+        );
+        cmd.env(key, value);
     }
 
+    // The dev server drops in-flight builds when files change again.
+    cmd.kill_on_drop(true);
     let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
 
     let stdout = BufReader::new(child.stdout.take().unwrap());
@@ -95,6 +222,13 @@ pub async fn run_cargo_build(
     let mut stdout = stdout.lines();
     let mut stderr = stderr.lines();
     let mut output_location: Option<PathBuf> = None;
+    let mut diagnostics = vec![];
+    let packaging = |message: logger::CargoPackagingMessage| {
+        logger::Message::CargoPackaging(match label {
+            Some(label) => message.labeled(label),
+            None => message,
+        })
+    };
 
     loop {
         use cargo_metadata::Message;
@@ -120,18 +254,20 @@ pub async fn run_cargo_build(
                 // TODO
                 None
             }
-            Message::CompilerMessage(compiler_message) => Some(logger::Message::CargoPackaging(
-                compiler_message.message.into(),
-            )),
-            Message::TextLine(value) => Some(logger::Message::CargoPackaging(value.into())),
+            Message::CompilerMessage(compiler_message) => {
+                let diagnostic = compiler_message.message;
+                if matches!(
+                    diagnostic.level,
+                    DiagnosticLevel::Error | DiagnosticLevel::Ice
+                ) {
+                    diagnostics.push(diagnostic.clone());
+                }
+                Some(packaging(diagnostic.into()))
+            }
+            Message::TextLine(value) => Some(packaging(value.into())),
             Message::BuildFinished(build_finished) => {
                 if !build_finished.success {
-                    if context.serve {
-                        // todo!()
-                        return Err(eyre!("Cargo build failed"));
-                    } else {
-                        return Err(eyre!("Cargo build failed"));
-                    }
+                    return Err(CargoBuildError { diagnostics }.into());
                 }
                 Some(logger::Message::CargoBuildFinished)
             }
@@ -145,3 +281,26 @@ pub async fn run_cargo_build(
 
     Ok(output_location)
 }
+
+#[test]
+fn test_append_rustflags() {
+    let rustflags = ["--cfg erase_components"];
+    assert_eq!(
+        append_rustflags(None, None, &rustflags),
+        ("RUSTFLAGS", "--cfg erase_components".to_string())
+    );
+    assert_eq!(
+        append_rustflags(None, Some("-Ctarget-cpu=native".to_string()), &rustflags),
+        (
+            "RUSTFLAGS",
+            "-Ctarget-cpu=native --cfg erase_components".to_string()
+        )
+    );
+    assert_eq!(
+        append_rustflags(Some("-C\x1fdebuginfo=0".to_string()), None, &rustflags),
+        (
+            "CARGO_ENCODED_RUSTFLAGS",
+            "-C\x1fdebuginfo=0\x1f--cfg\x1ferase_components".to_string()
+        )
+    );
+}
@@ -0,0 +1,300 @@
+use super::{assets::BundledAsset, client_assets_dir, compress::compress, wasm::main_bundle_names};
+use crate::{
+    config::{CompressAlgorithm, SizeBudget, SizeMeasure},
+    context::Context,
+    logger,
+};
+use color_eyre::{eyre::eyre, owo_colors::OwoColorize};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    io,
+    path::{Path, PathBuf},
+};
+use tokio::{fs, task};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+struct FileSize {
+    raw: u64,
+    gzip: u64,
+    brotli: u64,
+}
+
+impl FileSize {
+    fn measure(content: &[u8]) -> io::Result<Self> {
+        Ok(Self {
+            raw: content.len() as u64,
+            gzip: compress(content, CompressAlgorithm::Gzip)?.len() as u64,
+            brotli: compress(content, CompressAlgorithm::Br)?.len() as u64,
+        })
+    }
+
+    fn get(&self, measure: SizeMeasure) -> u64 {
+        match measure {
+            SizeMeasure::Raw => self.raw,
+            SizeMeasure::Gzip => self.gzip,
+            SizeMeasure::Brotli => self.brotli,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FileKind {
+    Wasm,
+    Js,
+    Asset,
+}
+
+#[derive(Debug)]
+struct ReportFile {
+    kind: FileKind,
+    /// Stays the same across builds, unlike the hashed file name.
+    key: String,
+    path: PathBuf,
+    size: FileSize,
+}
+
+/// Prints the sizes of the main bundle and the collected assets,
+/// compared with the previous `thaw build`, and checks `build.size_budget`.
+pub async fn report_sizes(context: &Context, assets: &[BundledAsset]) -> color_eyre::Result<()> {
+    context
+        .logger
+        .send(logger::Message::Build("Measuring bundle sizes".to_string()))
+        .await?;
+
+    let assets_dir = client_assets_dir(context);
+    let (js_name, wasm_name) = main_bundle_names(context).await?;
+    let mut files = vec![
+        (
+            FileKind::Wasm,
+            "entry.wasm".to_string(),
+            assets_dir.join(wasm_name),
+        ),
+        (
+            FileKind::Js,
+            "entry.js".to_string(),
+            assets_dir.join(js_name),
+        ),
+    ];
+    for asset in assets {
+        let source = &asset.absolute_source_path;
+        let key = source.strip_prefix(&context.current_dir).unwrap_or(source);
+        files.push((
+            FileKind::Asset,
+            key.to_string_lossy().replace('\\', "/"),
+            asset.output_path.clone(),
+        ));
+    }
+    let files = task::spawn_blocking(move || {
+        files
+            .into_par_iter()
+            .map(|(kind, key, path)| {
+                let size = FileSize::measure(&std::fs::read(&path)?)?;
+                Ok(ReportFile {
+                    kind,
+                    key,
+                    path,
+                    size,
+                })
+            })
+            .collect::<io::Result<Vec<_>>>()
+    })
+    .await??;
+
+    let report_path = report_path(context)?;
+    let previous = match fs::read_to_string(&report_path).await {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
+        Err(_) => BTreeMap::new(),
+    };
+
+    let budget = &context.config.build.size_budget;
+    let exceeded = check_budget(budget, &files);
+    // A build over budget is not kept as the baseline, so that a rerun still shows the growth.
+    if exceeded.is_empty() {
+        let current = files
+            .iter()
+            .map(|file| (file.key.clone(), file.size))
+            .collect::<BTreeMap<_, _>>();
+        if let Some(parent) = report_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::write(&report_path, serde_json::to_string_pretty(&current)?).await?;
+    }
+    context
+        .logger
+        .send(logger::Message::SizeReport(format_report(
+            &files,
+            &previous,
+            &context.out_dir,
+            budget,
+        )))
+        .await?;
+
+    if exceeded.is_empty() {
+        Ok(())
+    } else {
+        Err(eyre!("Size budget exceeded:\n  {}", exceeded.join("\n  ")))
+    }
+}
+
+fn report_path(context: &Context) -> color_eyre::Result<PathBuf> {
+    Ok(context
+        .target_dir
+        .join("thaw-cli")
+        .join("size-report")
+        .join(if context.config.release {
+            "release"
+        } else {
+            "debug"
+        })
+        .join(format!("{}.json", context.cargo_package_name())))
+}
+
+fn budget_limit(budget: &SizeBudget, kind: FileKind) -> Option<u64> {
+    match kind {
+        FileKind::Wasm => budget.wasm,
+        FileKind::Js => budget.js,
+        FileKind::Asset => budget.asset,
+    }
+}
+
+/// Returns a description of every exceeded limit.
+fn check_budget(budget: &SizeBudget, files: &[ReportFile]) -> Vec<String> {
+    let mut exceeded = vec![];
+    for file in files {
+        let size = file.size.get(budget.measure);
+        if let Some(limit) = budget_limit(budget, file.kind)
+            && size > limit
+        {
+            exceeded.push(format!(
+                "{} is {}, the limit is {}",
+                file.key,
+                format_size(size),
+                format_size(limit)
+            ));
+        }
+    }
+    let total = files
+        .iter()
+        .map(|file| file.size.get(budget.measure))
+        .sum::<u64>();
+    if let Some(limit) = budget.total
+        && total > limit
+    {
+        exceeded.push(format!(
+            "total is {}, the limit is {}",
+            format_size(total),
+            format_size(limit)
+        ));
+    }
+    exceeded
+}
+
+fn format_report(
+    files: &[ReportFile],
+    previous: &BTreeMap<String, FileSize>,
+    out_dir: &Path,
+    budget: &SizeBudget,
+) -> String {
+    let paths = files
+        .iter()
+        .map(|file| {
+            file.path
+                .strip_prefix(out_dir)
+                .unwrap_or(&file.path)
+                .to_string_lossy()
+                .replace('\\', "/")
+        })
+        .collect::<Vec<_>>();
+    let width = paths.iter().map(|path| path.len()).max().unwrap_or(0);
+
+    let mut lines = vec![];
+    for (file, path) in files.iter().zip(paths) {
+        let raw = format!("{:>10}", format_size(file.size.raw));
+        let over_budget = budget_limit(budget, file.kind)
+            .is_some_and(|limit| file.size.get(budget.measure) > limit);
+        let raw = if over_budget {
+            raw.red().bold().to_string()
+        } else {
+            raw.bold().to_string()
+        };
+        let delta = match previous.get(&file.key) {
+            Some(previous) => format_delta(file.size.raw, previous.raw),
+            None if previous.is_empty() => String::new(),
+            None => "new".cyan().to_string(),
+        };
+        lines.push(format!(
+            "{}  {raw} {} {}",
+            format!("{path:<width$}").dimmed(),
+            format!(
+                "│ gzip: {:>10} │ brotli: {:>10}",
+                format_size(file.size.gzip),
+                format_size(file.size.brotli)
+            )
+            .dimmed(),
+            delta
+        ));
+    }
+    lines.join("\n")
+}
+
+fn format_delta(size: u64, previous: u64) -> String {
+    if size > previous {
+        format!("+{}", format_size(size - previous))
+            .red()
+            .to_string()
+    } else if size < previous {
+        format!("-{}", format_size(previous - size))
+            .green()
+            .to_string()
+    } else {
+        String::new()
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    if bytes >= 1_000_000 {
+        format!("{:.2} MB", bytes as f64 / 1_000_000.0)
+    } else {
+        format!("{:.2} kB", bytes as f64 / 1_000.0)
+    }
+}
+
+#[test]
+fn test_check_budget() {
+    let file = |kind, key: &str, raw, gzip| ReportFile {
+        kind,
+        key: key.to_string(),
+        path: PathBuf::from(key),
+        size: FileSize {
+            raw,
+            gzip,
+            brotli: gzip,
+        },
+    };
+    let files = [
+        file(FileKind::Wasm, "entry.wasm", 3_000_000, 900_000),
+        file(FileKind::Js, "entry.js", 40_000, 10_000),
+    ];
+
+    let budget = SizeBudget {
+        wasm: Some(1_000_000),
+        total: Some(900_000),
+        measure: SizeMeasure::Gzip,
+        ..Default::default()
+    };
+    assert_eq!(
+        check_budget(&budget, &files),
+        vec!["total is 910.00 kB, the limit is 900.00 kB"]
+    );
+
+    let budget = SizeBudget {
+        wasm: Some(1_000_000),
+        ..Default::default()
+    };
+    assert_eq!(
+        check_budget(&budget, &files),
+        vec!["entry.wasm is 3.00 MB, the limit is 1.00 MB"]
+    );
+}
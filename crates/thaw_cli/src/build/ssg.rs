@@ -0,0 +1,196 @@
+use super::cargo_build_exe_name;
+use crate::{context::Context, logger, server::ssr::default_env};
+use color_eyre::eyre::eyre;
+use regex::Regex;
+use std::{
+    collections::{HashSet, VecDeque},
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::Duration,
+};
+use tokio::{
+    fs,
+    io::{AsyncBufRead, AsyncBufReadExt, BufReader},
+    net::TcpListener,
+    process::Command,
+    time,
+};
+
+/// Boots the built SSR executable on an ephemeral port and writes the
+/// configured routes as static HTML files into the client directory.
+pub async fn prerender(context: &Context) -> color_eyre::Result<()> {
+    context
+        .logger
+        .send(logger::Message::Build("Prerendering routes".to_string()))
+        .await?;
+
+    let client_out_dir = context.out_dir.join("client");
+    let server_out_dir = context.out_dir.join("server");
+
+    let addr = {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        listener.local_addr()?
+    };
+
+    let mut cmd = Command::new(server_out_dir.join(cargo_build_exe_name(context)?));
+    cmd.current_dir(&server_out_dir);
+    cmd.envs(default_env(context)?);
+    cmd.envs(context.env.cloned_into_iter());
+    cmd.env("LEPTOS_SITE_ADDR", addr.to_string());
+    cmd.env("LEPTOS_SITE_ROOT", &client_out_dir);
+    // Otherwise the live-reload script ends up in the static HTML.
+    cmd.env_remove("LEPTOS_WATCH");
+    let mut child = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    // The server output explains a server that fails to start.
+    forward_output(context, BufReader::new(child.stdout.take().unwrap()));
+    forward_output(context, BufReader::new(child.stderr.take().unwrap()));
+
+    let base_url = format!("http://{addr}");
+    let client = reqwest::Client::new();
+
+    let mut ready = false;
+    for _ in 0..120 {
+        if let Some(status) = child.try_wait()? {
+            return Err(eyre!(
+                "The SSR server exited with {status} before prerendering"
+            ));
+        }
+        if client.get(&base_url).send().await.is_ok() {
+            ready = true;
+            break;
+        }
+        time::sleep(Duration::from_millis(250)).await;
+    }
+    if !ready {
+        return Err(eyre!("Timed out waiting for the SSR server at {base_url}"));
+    }
+
+    let link_re = Regex::new(r#"<a\s[^>]*href="([^"]*)""#)?;
+    let mut queue = context
+        .config
+        .build
+        .prerender
+        .routes
+        .iter()
+        .cloned()
+        .collect::<VecDeque<_>>();
+    let mut visited = queue.iter().cloned().collect::<HashSet<_>>();
+    let configured_routes = visited.clone();
+
+    while let Some(route) = queue.pop_front() {
+        context
+            .logger
+            .send(logger::Message::Build(format!("Prerendering {route}")))
+            .await?;
+
+        let response = client.get(format!("{base_url}{route}")).send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            if configured_routes.contains(&route) {
+                return Err(eyre!(
+                    "Failed to prerender {route}: the server responded with {status}"
+                ));
+            }
+            // A crawled link may point at a page that is not meant to be prerendered.
+            context
+                .logger
+                .send(logger::Message::CargoPackaging(
+                    format!("warning: skipped {route}, the server responded with {status}").into(),
+                ))
+                .await?;
+            continue;
+        }
+        let html = response.text().await?;
+
+        if context.config.build.prerender.crawl_links {
+            for link in extract_links(&link_re, &html) {
+                if visited.insert(link.clone()) {
+                    queue.push_back(link);
+                }
+            }
+        }
+
+        let path = route_file_path(&client_out_dir, &route);
+        fs::create_dir_all(path.parent().unwrap()).await?;
+        fs::write(path, html).await?;
+    }
+
+    child.kill().await?;
+    Ok(())
+}
+
+/// Sends the lines of the server output to the logger, prefixed with `[server]`.
+fn forward_output(context: &Context, output: impl AsyncBufRead + Unpin + Send + 'static) {
+    let logger = (*context.logger).clone();
+    tokio::spawn(async move {
+        let mut lines = output.lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let message = logger::CargoPackagingMessage::from(line).labeled("server");
+            if logger
+                .send(logger::Message::CargoPackaging(message))
+                .await
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
+}
+
+/// Maps a route to its `index.html` file, e.g. `/blog/post` to `blog/post/index.html`.
+fn route_file_path(client_out_dir: &Path, route: &str) -> PathBuf {
+    let mut path = client_out_dir.to_path_buf();
+    for segment in route.split('/').filter(|segment| !segment.is_empty()) {
+        path.push(segment);
+    }
+    path.join("index.html")
+}
+
+/// Collects same-origin page links, skipping links to files such as assets.
+fn extract_links(link_re: &Regex, html: &str) -> Vec<String> {
+    link_re
+        .captures_iter(html)
+        .filter_map(|caps| {
+            let href = caps.get(1)?.as_str();
+            if !href.starts_with('/') || href.starts_with("//") {
+                return None;
+            }
+            let path = href.split(['?', '#']).next()?;
+            let file_name = path.rsplit('/').next()?;
+            if file_name.contains('.') {
+                return None;
+            }
+            Some(path.to_string())
+        })
+        .collect()
+}
+
+#[test]
+fn test_route_file_path() {
+    let client_out_dir = PathBuf::from("dist").join("client");
+    assert_eq!(
+        route_file_path(&client_out_dir, "/"),
+        client_out_dir.join("index.html")
+    );
+    assert_eq!(
+        route_file_path(&client_out_dir, "/blog/post/"),
+        client_out_dir.join("blog").join("post").join("index.html")
+    );
+}
+
+#[test]
+fn test_extract_links() {
+    let link_re = Regex::new(r#"<a\s[^>]*href="([^"]*)""#).unwrap();
+    let html = r#"
+        <a href="/about">About</a>
+        <a class="nav" href="/blog?page=2#top">Blog</a>
+        <a href="https://leptos.dev">Leptos</a>
+        <a href="//cdn.example.com/x">CDN</a>
+        <a href="/assets/report.pdf">Report</a>
+    "#;
+    assert_eq!(extract_links(&link_re, html), vec!["/about", "/blog"]);
+}
@@ -1,7 +1,116 @@
-use crate::{context::Context, server::ssr::default_env};
-use std::path::Path;
+use super::{cargo_config_args, dep_info::read_dep_info, hydrate, run_cargo_build};
+use crate::{
+    build::wasm::{HASH_FILE_NAME, build_wasm_path},
+    context::Context,
+    server::ssr::default_env,
+};
+use color_eyre::eyre::eyre;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
 use tokio::{fs, io::AsyncWriteExt};
 
+pub fn cargo_build_args(context: &Context) -> Vec<String> {
+    let config = &context.config.build.server;
+    let mut args = vec![
+        format!("--package={}", context.server_package_name()),
+        format!("--bin={}", context.server_bin_name()),
+    ];
+    if let Some(target) = &config.target {
+        args.push(format!("--target={target}"));
+    }
+    args.extend(cargo_config_args(context, config, &["ssr"]));
+    args
+}
+
+/// Runs the hydrate and server builds concurrently in the shared target directory,
+/// returns the server executable.
+pub async fn cargo_build(context: &Context) -> color_eyre::Result<PathBuf> {
+    let (_, exe_path) = tokio::try_join!(cargo_build_client(context), cargo_build_server(context))?;
+    Ok(exe_path)
+}
+
+pub async fn cargo_build_client(context: &Context) -> color_eyre::Result<()> {
+    run_cargo_build(
+        context,
+        hydrate::cargo_build_args(context),
+        &context.config.build.client,
+        Some("client"),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Returns the server executable.
+pub async fn cargo_build_server(context: &Context) -> color_eyre::Result<PathBuf> {
+    let exe_path = run_cargo_build(
+        context,
+        cargo_build_args(context),
+        &context.config.build.server,
+        Some("server"),
+    )
+    .await?;
+    exe_path.ok_or_else(|| {
+        eyre!(
+            "Cargo did not report the server executable `{}`",
+            context.server_bin_name()
+        )
+    })
+}
+
+/// Whether the changed files are compiled into the hydrate and the server build, from the
+/// dep-info of the last builds. Code behind `#[cfg(feature = "ssr")]` is only in the server's.
+/// Files in neither, like new modules, and a missing dep-info rebuild both.
+pub async fn affected_builds(context: &Context, paths: &[PathBuf]) -> (bool, bool) {
+    let client_dep_info = build_wasm_path(context)
+        .ok()
+        .map(|path| path.with_extension("d"));
+    let (Some(client_deps), Some(server_deps)) = (
+        match client_dep_info {
+            Some(path) => read_dep_info(&path).await,
+            None => None,
+        },
+        read_dep_info(&server_dep_info_path(context)).await,
+    ) else {
+        return (true, true);
+    };
+
+    // The watcher and rustc may spell the same file differently, e.g. through symlinks.
+    let canonicalize = |path: &Path| std::fs::canonicalize(path).unwrap_or(path.to_path_buf());
+    let client_deps = client_deps
+        .iter()
+        .map(|path| canonicalize(path))
+        .collect::<HashSet<_>>();
+    let server_deps = server_deps
+        .iter()
+        .map(|path| canonicalize(path))
+        .collect::<HashSet<_>>();
+
+    let (mut client, mut server) = (false, false);
+    for path in paths {
+        let path = canonicalize(path);
+        let in_client = client_deps.contains(&path);
+        let in_server = server_deps.contains(&path);
+        if !in_client && !in_server {
+            return (true, true);
+        }
+        client |= in_client;
+        server |= in_server;
+    }
+    (client, server)
+}
+
+fn server_dep_info_path(context: &Context) -> PathBuf {
+    let config = &context.config.build.server;
+    let mut path = context.target_dir.clone();
+    if let Some(target) = &config.target {
+        path.push(target);
+    }
+    path.push(config.profile_dir(context.config.release));
+    path.join(format!("{}.d", context.server_bin_name()))
+}
+
 pub async fn build_env_file(context: &Context, out_dir: &Path) -> color_eyre::Result<()> {
     let envs = default_env(context)?;
     let envs = envs
@@ -12,5 +121,11 @@ pub async fn build_env_file(context: &Context, out_dir: &Path) -> color_eyre::Re
     let path = out_dir.join(".env");
     let mut file = fs::File::create(path).await?;
     file.write_all(envs.as_bytes()).await?;
+
+    // The Leptos server looks for the hashes next to its executable.
+    let hash_file = context.wasm_bindgen_dir.join(HASH_FILE_NAME);
+    if fs::try_exists(&hash_file).await? {
+        fs::copy(hash_file, out_dir.join(HASH_FILE_NAME)).await?;
+    }
     Ok(())
 }
@@ -1,21 +1,22 @@
 use crate::{
+    build::{content_hash, hot_patch},
+    config::{WasmBindgenTarget, WasmOptLevel},
     context::Context,
     logger,
     utils::{DotEyre, fs::copy_dir_all, wasm_opt_bin_path},
 };
+use color_eyre::eyre::eyre;
 use std::path::{Path, PathBuf};
 use tokio::process::Command;
 use wasm_bindgen_cli_support::Bindgen;
 
-fn build_wasm_path(context: &Context) -> color_eyre::Result<PathBuf> {
+pub fn build_wasm_path(context: &Context) -> color_eyre::Result<PathBuf> {
+    let client = &context.config.build.client;
     let wasm_path = context.target_dir.join(format!(
-        "wasm32-unknown-unknown/{}/{}.wasm",
-        if context.config.release {
-            "release"
-        } else {
-            "debug"
-        },
-        context.cargo_package_name()?
+        "{}/{}/{}.wasm",
+        client.client_target(),
+        client.profile_dir(context.config.release),
+        context.wasm_artifact_name()
     ));
     Ok(wasm_path)
 }
@@ -40,46 +41,203 @@ pub async fn wasm_bindgen(
         build_wasm_path(context)?
     };
 
+    let config = &context.config.build.wasm_bindgen;
+    if context.ssr && config.target != WasmBindgenTarget::Web {
+        return Err(eyre!(
+            "SSR only supports `build.wasm_bindgen.target = \"web\"`, the Leptos server imports the bundle as an ES module"
+        ));
+    }
+
     let mut bindgen = Bindgen::new();
-    let bindgen = bindgen.input_path(input_path).web(true).dot_eyre()?;
-    bindgen.generate(&context.wasm_bindgen_dir).dot_eyre()?;
+    bindgen
+        .input_path(input_path)
+        .out_name(&context.output_name()?);
+    match config.target {
+        WasmBindgenTarget::Web => bindgen.web(true).dot_eyre()?,
+        WasmBindgenTarget::NoModules => bindgen.no_modules(true).dot_eyre()?,
+        WasmBindgenTarget::Bundler => bindgen.bundler(true).dot_eyre()?,
+    };
+    if config.reference_types {
+        #[allow(deprecated)]
+        bindgen.reference_types(true);
+    }
+    bindgen
+        .typescript(config.typescript)
+        .debug(config.debug)
+        .keep_debug(config.keep_debug)
+        .demangle(config.demangle)
+        .omit_default_module_path(config.omit_default_module_path)
+        .generate(&context.wasm_bindgen_dir)
+        .dot_eyre()?;
 
     copy_dir_all(&context.wasm_bindgen_dir, out_dir).await?;
 
-    let package_name = context.cargo_package_name()?;
-    let wasm_name = format!("{package_name}_bg.wasm");
+    let output_name = context.output_name()?;
+    let wasm_name = format!("{output_name}_bg.wasm");
 
     let wasm_path = context.wasm_bindgen_dir.join(&wasm_name);
     let mut out_wasm_path = out_dir.join(wasm_name);
     if context.ssr {
         tokio::fs::remove_file(&out_wasm_path).await?;
-        out_wasm_path = out_dir.join(format!("{package_name}.wasm"))
+        out_wasm_path = out_dir.join(format!("{output_name}.wasm"))
     }
     wasm_opt(context, &wasm_path, &out_wasm_path).await?;
+    if is_hash_filenames(context) {
+        hash_filenames(context, out_dir, &out_wasm_path).await?;
+    }
+
+    context.plugins.after_wasm_bindgen(context, out_dir).await?;
 
     Ok(())
 }
 
 async fn wasm_opt(context: &Context, input_path: &Path, out_path: &Path) -> color_eyre::Result<()> {
+    // Hot patches are matched against the exports and table of the unoptimized module.
+    let level = if hot_patch::is_enabled(context) {
+        WasmOptLevel::False
+    } else {
+        wasm_opt_level(context)
+    };
+    let Some(level) = level.arg() else {
+        tokio::fs::copy(input_path, out_path).await?;
+        return Ok(());
+    };
+
     context
         .logger
         .send(logger::Message::Build("Optimize WASM".to_string()))
         .await?;
-    let path = wasm_opt_bin_path().await?;
-    // wasm_opt::OptimizationOptions::new_optimize_for_size_aggressively()
-    let args = vec![
-        input_path.to_str().unwrap(),
-        "-o",
-        out_path.to_str().unwrap(),
-        "-Oz",
-        "--enable-reference-types",
-        "--enable-bulk-memory",
-        "--enable-mutable-globals",
-        "--enable-nontrapping-float-to-int",
-        "--debuginfo",
+    let config = &context.config.build.wasm_opt;
+    let path = wasm_opt_bin_path(config).await?;
+    let mut args = vec![
+        input_path.as_os_str().to_owned(),
+        "-o".into(),
+        out_path.as_os_str().to_owned(),
+        level.into(),
     ];
+    for feature in DEFAULT_WASM_FEATURES
+        .iter()
+        .copied()
+        .chain(config.features.iter().map(String::as_str))
+    {
+        args.push(format!("--enable-{feature}").into());
+    }
+    if config.debuginfo {
+        args.push("--debuginfo".into());
+    }
+    args.extend(config.args.iter().map(Into::into));
 
-    Command::new(path).args(args).spawn()?.wait().await?;
+    let output = Command::new(path).args(args).output().await?;
+    if !output.status.success() {
+        for line in String::from_utf8_lossy(&output.stderr).lines() {
+            context
+                .logger
+                .send(logger::Message::CargoPackaging(line.to_string().into()))
+                .await?;
+        }
+        return Err(eyre!("wasm-opt failed with {}", output.status));
+    }
 
     Ok(())
 }
+
+const DEFAULT_WASM_FEATURES: [&str; 4] = [
+    "reference-types",
+    "bulk-memory",
+    "mutable-globals",
+    "nontrapping-float-to-int",
+];
+
+/// Debug dev builds skip wasm-opt unless a level is configured.
+fn wasm_opt_level(context: &Context) -> WasmOptLevel {
+    match context.config.build.wasm_opt.level {
+        Some(level) => level,
+        None if context.serve && !context.config.release => WasmOptLevel::False,
+        None => WasmOptLevel::Oz,
+    }
+}
+
+/// Written to the wasm-bindgen directory, in the format the Leptos server reads.
+pub const HASH_FILE_NAME: &str = "hash.txt";
+
+#[inline]
+pub fn is_hash_filenames(context: &Context) -> bool {
+    // Bundler output imports the WASM by its file name.
+    context.config.build.hash_filenames
+        && !context.serve
+        && context.config.build.wasm_bindgen.target != WasmBindgenTarget::Bundler
+}
+
+/// Renames the main JS/WASM bundle with a content hash, the JS import of the WASM included.
+async fn hash_filenames(
+    context: &Context,
+    out_dir: &Path,
+    wasm_path: &Path,
+) -> color_eyre::Result<()> {
+    let output_name = context.output_name()?;
+
+    let wasm_hash = content_hash(&tokio::fs::read(wasm_path).await?);
+    let wasm_name = hashed_file_name(wasm_path, &wasm_hash);
+    tokio::fs::rename(wasm_path, out_dir.join(&wasm_name)).await?;
+
+    let js_path = out_dir.join(format!("{output_name}.js"));
+    let js = tokio::fs::read_to_string(&js_path).await?.replace(
+        &format!("'{output_name}_bg.wasm'"),
+        &format!("'{wasm_name}'"),
+    );
+    let js_hash = content_hash(js.as_bytes());
+    tokio::fs::write(out_dir.join(hashed_file_name(&js_path, &js_hash)), js).await?;
+    tokio::fs::remove_file(js_path).await?;
+
+    tokio::fs::write(
+        context.wasm_bindgen_dir.join(HASH_FILE_NAME),
+        format!("js: {js_hash}\nwasm: {wasm_hash}\n"),
+    )
+    .await?;
+    Ok(())
+}
+
+/// File names of the main JS and WASM bundle in the assets directory.
+pub async fn main_bundle_names(context: &Context) -> color_eyre::Result<(String, String)> {
+    let output_name = context.output_name()?;
+    let js_name = PathBuf::from(format!("{output_name}.js"));
+    let wasm_name = PathBuf::from(if context.ssr {
+        format!("{output_name}.wasm")
+    } else {
+        format!("{output_name}_bg.wasm")
+    });
+
+    let hash_file = tokio::fs::read_to_string(context.wasm_bindgen_dir.join(HASH_FILE_NAME))
+        .await
+        .unwrap_or_default();
+    let name = |path: &Path, file: &str| match hash_file_entry(&hash_file, file) {
+        Some(hash) => hashed_file_name(path, hash),
+        None => path.display().to_string(),
+    };
+    Ok((name(&js_name, "js"), name(&wasm_name, "wasm")))
+}
+
+fn hash_file_entry<'a>(hash_file: &'a str, file: &str) -> Option<&'a str> {
+    hash_file.lines().find_map(|line| {
+        let (key, hash) = line.split_once(':')?;
+        (key.trim() == file).then_some(hash.trim())
+    })
+}
+
+fn hashed_file_name(path: &Path, hash: &str) -> String {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    match path.extension() {
+        Some(extension) => format!("{stem}.{hash}.{}", extension.to_string_lossy()),
+        None => format!("{stem}.{hash}"),
+    }
+}
+
+#[test]
+fn test_hashed_file_name() {
+    assert_eq!(
+        hashed_file_name(Path::new("dist/assets/app_bg.wasm"), "0123abcd"),
+        "app_bg.0123abcd.wasm"
+    );
+    assert_eq!(hash_file_entry("js: 01\nwasm: 02\n", "wasm"), Some("02"));
+    assert_eq!(hash_file_entry("js: 01\n", "css"), None);
+}
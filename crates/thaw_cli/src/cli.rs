@@ -1,5 +1,5 @@
 use crate::{
-    commands::{BuildCommands, Commands, ServeCommands},
+    commands::{BuildCommands, Commands, PreviewCommands, ServeCommands, ToolsCommands},
     context::Context,
 };
 use clap::Parser;
@@ -9,6 +9,10 @@ use clap::Parser;
 pub struct Cli {
     #[command(subcommand)]
     commands: Commands,
+
+    /// The package to build, or the name of an app in Thaw.toml
+    #[arg(short, long, global = true)]
+    package: Option<String>,
 }
 
 impl Cli {
@@ -18,6 +22,19 @@ impl Cli {
         self.commands.run(context).await
     }
 
+    #[inline]
+    pub fn package(&self) -> Option<&str> {
+        self.package.as_deref()
+    }
+
+    #[inline]
+    pub fn tools(&self) -> Option<&ToolsCommands> {
+        match &self.commands {
+            Commands::Tools(tools_commands) => Some(tools_commands),
+            _ => None,
+        }
+    }
+
     #[inline]
     pub fn is_serve(&self) -> bool {
         matches!(self.commands, Commands::Serve(_))
@@ -26,14 +43,24 @@ impl Cli {
     #[inline]
     pub fn is_ssr(&self) -> bool {
         match &self.commands {
-            Commands::Build(build_commands) => matches!(build_commands, BuildCommands::Ssr),
+            Commands::Build(build_commands) => {
+                matches!(build_commands, BuildCommands::Ssr | BuildCommands::Ssg)
+            }
             Commands::Serve(serve_commands) => matches!(serve_commands, ServeCommands::Ssr(_)),
+            Commands::Preview(preview_commands) => {
+                matches!(preview_commands, PreviewCommands::Ssr(_))
+            }
+            Commands::Analyze(args) => args.ssr,
+            Commands::Tools(_) => false,
         }
     }
 
     pub fn mode(&self) -> &'static str {
         match &self.commands {
-            Commands::Build(_) => "production",
+            Commands::Build(_)
+            | Commands::Preview(_)
+            | Commands::Analyze(_)
+            | Commands::Tools(_) => "production",
             Commands::Serve(_) => "development",
         }
     }
@@ -13,7 +13,8 @@ function debounceReload(time) {
 const pageReload = debounceReload(50);
 
 console.debug("[thaw-cli] connecting...");
-const socket = new WebSocket(`ws://${window.location.host}/@thaw_cli/client`);
+const socketProtocol = window.location.protocol === "https:" ? "wss" : "ws";
+const socket = new WebSocket(`${socketProtocol}://${window.location.host}/@thaw_cli/client`);
 
 socket.addEventListener("message", async (event) => {
     handleMessage(JSON.parse(event.data));
@@ -35,8 +36,28 @@ async function handleMessage(payload) {
             console.debug(`[thaw-cli] connected.`);
             break;
         case "RefreshPage":
+            clearErrorOverlay();
             pageReload();
             break;
+        case "AssetUpdate":
+            clearErrorOverlay();
+            for (const path of payload.paths) {
+                updateStyle(path);
+            }
+            break;
+        case "BuildError":
+            createErrorOverlay(payload.errors);
+            break;
+        case "HotPatch":
+            clearErrorOverlay();
+            try {
+                await applyHotPatch(payload);
+                console.debug("[thaw-cli] hot patched.");
+            } catch (error) {
+                console.error("[thaw-cli] failed to apply the hot patch.", error);
+                socket.send(JSON.stringify({ type: "HotPatchFailed", error: String(error) }));
+            }
+            break;
         case "Custom":
             if (payload.event === "thaw-cli:ws:disconnect") {
                 const socket = payload.data.webSocket;
@@ -48,6 +69,232 @@ async function handleMessage(payload) {
     }
 }
 
+function updateStyle(path) {
+    const links = document.querySelectorAll('link[rel="stylesheet"]');
+    const link = Array.from(links).find((link) => {
+        return new URL(link.href, window.location.href).pathname === path;
+    });
+    if (!link) {
+        pageReload();
+        return;
+    }
+    // Swap in a new link element once loaded to avoid a flash of unstyled content.
+    const newLink = link.cloneNode();
+    newLink.href = `${path}?t=${Date.now()}`;
+    newLink.addEventListener("load", () => link.remove(), { once: true });
+    newLink.addEventListener("error", () => link.remove(), { once: true });
+    link.after(newLink);
+}
+
+// Loads the patch as a side module of the running app, like a dynamic linker,
+// then points the table slots of the changed functions at the new code.
+async function applyHotPatch(patch) {
+    const wasm = window.__thaw_cli_wasm;
+    if (!wasm) {
+        throw new Error("The app module was not found");
+    }
+    const memory = wasm[patch.memory_export];
+    const table = wasm[patch.table_export];
+
+    const memoryAlignment = 2 ** patch.memory_alignment;
+    const pages = Math.ceil((patch.memory_size + memoryAlignment) / 65536);
+    const memoryBase = alignTo(memory.grow(pages) * 65536, memoryAlignment);
+    const tableBase = alignTo(table.length, 2 ** patch.table_alignment);
+    table.grow(tableBase - table.length + patch.table_size);
+
+    const module = await WebAssembly.compileStreaming(fetch(patch.lib));
+    const env = {
+        memory,
+        __indirect_function_table: table,
+        __memory_base: new WebAssembly.Global({ value: "i32", mutable: false }, memoryBase),
+        __table_base: new WebAssembly.Global({ value: "i32", mutable: false }, tableBase),
+    };
+    const gotMem = {};
+    const gotFunc = {};
+    for (const { module: importModule, name } of WebAssembly.Module.imports(module)) {
+        if (importModule === "GOT.mem") {
+            gotMem[name] = new WebAssembly.Global({ value: "i32", mutable: true }, 0);
+        } else if (importModule === "GOT.func") {
+            gotFunc[name] = new WebAssembly.Global({ value: "i32", mutable: true }, 0);
+        } else if (importModule === "env" && !(name in env)) {
+            if (!(name in wasm)) {
+                throw new Error(`Unresolved symbol ${name}`);
+            }
+            env[name] = wasm[name];
+        }
+    }
+    const instance = await WebAssembly.instantiate(module, {
+        env,
+        "GOT.mem": gotMem,
+        "GOT.func": gotFunc,
+    });
+    const exports = instance.exports;
+
+    // Prefer the statics of the app, so that their state survives the patch.
+    for (const [name, global] of Object.entries(gotMem)) {
+        if (wasm[name] instanceof WebAssembly.Global) {
+            global.value = wasm[name].value;
+        } else if (exports[name] instanceof WebAssembly.Global) {
+            global.value = memoryBase + exports[name].value;
+        } else {
+            throw new Error(`Unresolved data symbol ${name}`);
+        }
+    }
+    for (const [name, global] of Object.entries(gotFunc)) {
+        const func = exports[name] ?? wasm[name];
+        if (!func) {
+            throw new Error(`Unresolved function ${name}`);
+        }
+        global.value = tableIndex(table, func);
+    }
+    exports.__wasm_apply_data_relocs?.();
+    exports.__wasm_call_ctors?.();
+
+    for (const [slot, name] of Object.entries(patch.table)) {
+        table.set(Number(slot), exports[name]);
+    }
+}
+
+let tableIndexes;
+
+function tableIndex(table, func) {
+    if (!tableIndexes) {
+        tableIndexes = new Map();
+        for (let index = 0; index < table.length; index++) {
+            const value = table.get(index);
+            if (value && !tableIndexes.has(value)) {
+                tableIndexes.set(value, index);
+            }
+        }
+    }
+    let index = tableIndexes.get(func);
+    if (index === undefined) {
+        index = table.grow(1);
+        table.set(index, func);
+        tableIndexes.set(func, index);
+    }
+    return index;
+}
+
+function alignTo(value, alignment) {
+    return Math.ceil(value / alignment) * alignment;
+}
+
+const overlayId = "thaw-cli-error-overlay";
+const overlayTemplate = `
+<style>
+.backdrop {
+    position: fixed;
+    z-index: 99999;
+    inset: 0;
+    overflow-y: auto;
+    margin: 0;
+    background: rgba(0, 0, 0, 0.66);
+    font-family: Menlo, Consolas, monospace;
+    --red: #ff5555;
+    --green: #50fa7b;
+    --yellow: #f1fa8c;
+    --blue: #6cb6ff;
+    --magenta: #ff79c6;
+    --cyan: #8be9fd;
+}
+.window {
+    position: relative;
+    width: 800px;
+    max-width: 80vw;
+    margin: 30px auto;
+    padding: 25px 40px;
+    color: #d8d8d8;
+    background: #181818;
+    border-radius: 6px 6px 8px 8px;
+    border-top: 8px solid var(--red);
+    box-shadow: 0 19px 38px rgba(0, 0, 0, 0.3), 0 15px 12px rgba(0, 0, 0, 0.22);
+}
+.title {
+    margin: 0 0 1em;
+    color: var(--red);
+    font-size: 16px;
+    font-weight: 600;
+}
+.close {
+    position: absolute;
+    top: 12px;
+    right: 16px;
+    border: none;
+    background: none;
+    color: #999;
+    font-size: 20px;
+    cursor: pointer;
+}
+.file {
+    margin: 1em 0 0.5em;
+    color: var(--cyan);
+}
+pre {
+    margin: 0 0 1em;
+    padding-bottom: 1em;
+    overflow-x: auto;
+    font-size: 13px;
+    line-height: 1.5;
+    border-bottom: 1px dotted #555;
+}
+.tip {
+    color: #999;
+    font-size: 13px;
+}
+</style>
+<div class="backdrop">
+    <div class="window">
+        <button class="close" title="Close">&times;</button>
+        <p class="title">[thaw-cli] Build failed</p>
+        <div class="errors"></div>
+        <div class="tip">Fix the errors and save the file to rebuild. Click outside, press Esc or the close button to dismiss.</div>
+    </div>
+</div>
+`;
+
+function createErrorOverlay(errors) {
+    clearErrorOverlay();
+
+    const overlay = document.createElement("div");
+    overlay.id = overlayId;
+    const root = overlay.attachShadow({ mode: "open" });
+    root.innerHTML = overlayTemplate;
+
+    const errorsElement = root.querySelector(".errors");
+    for (const error of errors) {
+        if (error.file) {
+            const file = document.createElement("div");
+            file.className = "file";
+            file.textContent = `${error.file}:${error.line}:${error.column}`;
+            errorsElement.appendChild(file);
+        }
+        const pre = document.createElement("pre");
+        pre.innerHTML = error.html;
+        errorsElement.appendChild(pre);
+    }
+
+    root.querySelector(".window").addEventListener("click", (event) => {
+        event.stopPropagation();
+    });
+    root.querySelector(".backdrop").addEventListener("click", clearErrorOverlay);
+    root.querySelector(".close").addEventListener("click", clearErrorOverlay);
+    document.addEventListener("keydown", closeOnEsc);
+
+    document.body.appendChild(overlay);
+}
+
+function clearErrorOverlay() {
+    document.getElementById(overlayId)?.remove();
+    document.removeEventListener("keydown", closeOnEsc);
+}
+
+function closeOnEsc(event) {
+    if (event.key === "Escape" || event.code === "Escape") {
+        clearErrorOverlay();
+    }
+}
+
 async function waitForSuccessfulPing(socketUrl, ms = 1e3) {
     async function ping() {
         const socket = new WebSocket(socketUrl, "thaw-cli-ping");
@@ -0,0 +1,176 @@
+use crate::{
+    build::{
+        analyze::{Analysis, Item, analyze},
+        client_assets_dir,
+        wasm::main_bundle_names,
+    },
+    context::Context,
+};
+use clap::Args;
+use color_eyre::eyre::eyre;
+use crossterm::style::Stylize;
+use std::{
+    fmt::Write,
+    path::{Path, PathBuf},
+};
+use tokio::fs;
+
+#[derive(Debug, Args)]
+pub struct AnalyzeArgs {
+    /// Analyze the WASM of `thaw build ssr` instead of `thaw build csr`
+    #[arg(long)]
+    pub ssr: bool,
+    /// Analyze this WASM file instead of the built one
+    #[arg(long)]
+    pub path: Option<PathBuf>,
+    /// Number of items to show in each table
+    #[arg(long, default_value_t = 20)]
+    pub top: usize,
+    /// Write the full analysis as JSON
+    #[arg(long)]
+    pub json: Option<PathBuf>,
+    /// Write the analysis as an HTML page
+    #[arg(long)]
+    pub html: Option<PathBuf>,
+}
+
+impl AnalyzeArgs {
+    pub async fn run(self, context: &Context) -> color_eyre::Result<()> {
+        let wasm_path = match self.path {
+            Some(path) => context.current_dir.join(path),
+            None => {
+                let (_, wasm_name) = main_bundle_names(context).await?;
+                client_assets_dir(context).join(wasm_name)
+            }
+        };
+        let wasm = fs::read(&wasm_path).await.map_err(|err| {
+            eyre!(
+                "Failed to read {}: {err}. Run `thaw build {}` first.",
+                wasm_path.display(),
+                if context.ssr { "ssr" } else { "csr" }
+            )
+        })?;
+        let analysis = analyze(&wasm)?;
+
+        print_analysis(&analysis, self.top);
+        if let Some(path) = self.json {
+            fs::write(
+                context.current_dir.join(path),
+                serde_json::to_string_pretty(&analysis)?,
+            )
+            .await?;
+        }
+        if let Some(path) = self.html {
+            fs::write(
+                context.current_dir.join(path),
+                analysis_html(&analysis, &wasm_path, self.top),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+fn tables(analysis: &Analysis) -> [(&'static str, &[Item]); 3] {
+    [
+        ("Functions", &analysis.functions),
+        ("Data segments", &analysis.data),
+        ("Crates", &analysis.crates),
+    ]
+}
+
+fn percent(size: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        size as f64 / total as f64 * 100.0
+    }
+}
+
+fn print_analysis(analysis: &Analysis, top: usize) {
+    for (title, items) in tables(analysis) {
+        println!("\n  {}", title.bold());
+        println!(
+            "  {}",
+            format!("{:>12} {:>8} {:>12}  Name", "Retained", "%", "Shallow").dark_grey()
+        );
+        for item in items.iter().take(top) {
+            println!(
+                "  {:>12} {:>7.2}% {:>12}  {}",
+                item.retained_size,
+                percent(item.retained_size, analysis.size),
+                item.shallow_size,
+                item.name
+            );
+        }
+        if items.len() > top {
+            println!(
+                "  {}",
+                format!("... and {} more", items.len() - top).dark_grey()
+            );
+        }
+    }
+    println!("\n  {} {} bytes", "Module size:".dark_grey(), analysis.size);
+}
+
+fn analysis_html(analysis: &Analysis, wasm_path: &Path, top: usize) -> String {
+    let mut html = String::new();
+    let title = escape_html(&wasm_path.display().to_string());
+    let _ = write!(
+        html,
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+body {{ font-family: system-ui, sans-serif; margin: 2rem; }}
+table {{ border-collapse: collapse; width: 100%; margin-bottom: 2rem; }}
+th, td {{ padding: 0.25rem 0.5rem; text-align: right; white-space: nowrap; }}
+th:last-child, td:last-child {{ text-align: left; white-space: normal; font-family: monospace; }}
+tr:nth-child(even) {{ background: #f5f5f5; }}
+.bar {{ background: #0078d4; height: 0.5rem; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<p>Module size: {} bytes</p>
+"#,
+        analysis.size
+    );
+    for (table_title, items) in tables(analysis) {
+        let _ = write!(
+            html,
+            "<h2>{table_title}</h2>\n<table>\n<tr><th>Retained</th><th>%</th><th></th><th>Shallow</th><th>Name</th></tr>\n"
+        );
+        for item in items.iter().take(top) {
+            let percent = percent(item.retained_size, analysis.size);
+            let _ = writeln!(
+                html,
+                r#"<tr><td>{}</td><td>{percent:.2}%</td><td style="width: 10rem"><div class="bar" style="width: {percent:.2}%"></div></td><td>{}</td><td>{}</td></tr>"#,
+                item.retained_size,
+                item.shallow_size,
+                escape_html(&item.name)
+            );
+        }
+        html.push_str("</table>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[test]
+fn test_escape_html() {
+    assert_eq!(
+        escape_html("<&T as core::fmt::Debug>::fmt"),
+        "&lt;&amp;T as core::fmt::Debug&gt;::fmt"
+    );
+}
@@ -1,7 +1,8 @@
 use crate::{
     build::{
-        assets, cargo_build_exe_name, clear_out_dir, collect_assets, copy_public_dir, csr, hydrate,
-        run_cargo_build, ssr, wasm_bindgen,
+        assets, cargo_build_exe_name, clear_out_dir, client_out_dir, collect_assets, compress_dir,
+        copy_public_dir, csr, report_sizes, run_cargo_build, ssg, ssr, wasm_bindgen,
+        write_manifest,
     },
     context::Context,
 };
@@ -15,6 +16,8 @@ pub enum BuildCommands {
     Csr,
     /// Server-side Rendering
     Ssr,
+    /// Static site generation, prerenders the SSR build into static HTML files
+    Ssg,
 }
 
 impl BuildCommands {
@@ -22,20 +25,29 @@ impl BuildCommands {
         self,
         context: &Arc<Context>,
     ) -> color_eyre::Result<Vec<assets::BundledAsset>> {
-        match self {
+        context.plugins.build_start(context).await?;
+
+        let assets = match self {
             Self::Csr => {
-                let wasm_path = run_cargo_build(context, csr::cargo_build_args(context)).await?;
+                let wasm_path = run_cargo_build(
+                    context,
+                    csr::cargo_build_args(context),
+                    &context.config.build.client,
+                    None,
+                )
+                .await?;
                 clear_out_dir(context).await?;
                 if !context.serve {
                     copy_public_dir(context, &context.out_dir).await?;
                 }
-                csr::build_index_html(context).await?;
                 fs::create_dir_all(&context.assets_dir).await?;
                 let assets = collect_assets(context, wasm_path, &context.assets_dir).await?;
                 wasm_bindgen(context, None, &context.assets_dir).await?;
-                Ok(assets)
+                // After wasm-bindgen, which decides the bundle file names.
+                csr::build_index_html(context).await?;
+                assets
             }
-            Self::Ssr => {
+            Self::Ssr | Self::Ssg => {
                 clear_out_dir(context).await?;
 
                 let client_out_dir = context.out_dir.join("client");
@@ -47,12 +59,8 @@ impl BuildCommands {
                     copy_public_dir(context, &client_out_dir).await?;
                 }
 
-                run_cargo_build(context, hydrate::cargo_build_args()).await?;
+                let exe_path = ssr::cargo_build(context).await?;
                 wasm_bindgen(context, None, &assets_dir).await?;
-
-                let exe_path = run_cargo_build(context, vec!["--features=ssr"])
-                    .await?
-                    .unwrap();
                 let assets = collect_assets(context, Some(exe_path.clone()), &assets_dir).await?;
                 fs::create_dir_all(&server_out_dir).await?;
                 fs::copy(
@@ -61,8 +69,19 @@ impl BuildCommands {
                 )
                 .await?;
                 ssr::build_env_file(context, &server_out_dir).await?;
-                Ok(assets)
+                if matches!(self, Self::Ssg) {
+                    ssg::prerender(context).await?;
+                }
+                assets
             }
+        };
+
+        if !context.serve {
+            compress_dir(context, &client_out_dir(context)).await?;
+            write_manifest(context, &assets).await?;
+            report_sizes(context, &assets).await?;
         }
+        context.plugins.write_bundle(context, &assets).await?;
+        Ok(assets)
     }
 }
@@ -1,11 +1,15 @@
+mod analyze;
 mod build;
+mod tools;
 
+pub use analyze::AnalyzeArgs;
 pub use build::BuildCommands;
+pub use tools::ToolsCommands;
 
 use crate::{
     context::Context,
     logger,
-    server::{csr, init_build_finished, ssr},
+    server::{bind_listener, csr, init_build_finished, preview, ssr},
 };
 use clap::{Args, Subcommand};
 use crossterm::style::Stylize;
@@ -20,11 +24,29 @@ pub enum Commands {
     /// Start Thaw CLI dev server in the current directory
     #[command(subcommand)]
     Serve(ServeCommands),
+    /// Locally preview the production build
+    #[command(subcommand)]
+    Preview(PreviewCommands),
+    /// Report the size of functions, data segments and crates in the built WASM
+    Analyze(AnalyzeArgs),
+    /// Manage the tools downloaded by Thaw CLI
+    #[command(subcommand)]
+    Tools(ToolsCommands),
 }
 
 impl Commands {
     pub async fn run(self, mut context: Context) -> color_eyre::Result<()> {
+        // Bind before building, so that a busy port fails fast
+        // and the actual port is used in the env.
+        let listener = if let Self::Serve(subcommmands) = &self {
+            subcommmands.override_config(&mut context);
+            Some(bind_listener(&mut context).await?)
+        } else {
+            None
+        };
+
         context.env.set_default(ssr::default_dev_env(&context)?);
+        context.plugins.config_resolved(&context)?;
 
         match self {
             Self::Build(subcommmands) => {
@@ -36,35 +58,41 @@ impl Commands {
                 .await
             }
             Self::Serve(subcommmands) => match subcommmands {
-                ServeCommands::Csr(ServeCsrArgs { open }) => {
-                    if let Some(open) = open {
-                        context.open = open;
-                    }
+                ServeCommands::Csr(_) => {
                     let context = Arc::new(context);
                     let assets = BuildCommands::Csr.run(&context).await?;
                     init_build_finished(&context).await?;
                     csr::DevServer::new(context)?
-                        .run(assets)
+                        .run(assets, listener.unwrap())
                         .await?
                         .wait_event()
                         .await?;
                     Ok(())
                 }
-                ServeCommands::Ssr(ServeSsrArgs { open }) => {
-                    if let Some(open) = open {
-                        context.open = open;
-                    }
+                ServeCommands::Ssr(_) => {
                     let context = Arc::new(context);
                     let assets = BuildCommands::Ssr.run(&context).await?;
                     init_build_finished(&context).await?;
                     ssr::DevServer::new(context)?
-                        .run(assets)
+                        .run(assets, listener.unwrap())
                         .await?
                         .wait_event()
                         .await?;
                     Ok(())
                 }
             },
+            Self::Preview(subcommmands) => match subcommmands {
+                PreviewCommands::Csr(PreviewArgs { open }) => {
+                    context.open = open.unwrap_or(context.config.preview.open);
+                    preview::run_csr(&context).await
+                }
+                PreviewCommands::Ssr(PreviewArgs { open }) => {
+                    context.open = open.unwrap_or(context.config.preview.open);
+                    preview::run_ssr(&context).await
+                }
+            },
+            Self::Analyze(args) => args.run(&context).await,
+            Self::Tools(_) => unreachable!("tools are run before the context is created"),
         }
     }
 }
@@ -77,11 +105,45 @@ pub enum ServeCommands {
     Ssr(ServeSsrArgs),
 }
 
+impl ServeCommands {
+    fn override_config(&self, context: &mut Context) {
+        let (open, host, port) = match self {
+            Self::Csr(args) => (args.open, args.host.clone(), args.port),
+            Self::Ssr(args) => (args.open, args.host.clone(), args.port),
+        };
+        if let Some(open) = open {
+            context.open = open;
+        }
+        if let Some(host) = host {
+            context.config.server.host = host;
+        }
+        if let Some(port) = port {
+            context.config.server.port = port;
+        }
+        if let Self::Csr(ServeCsrArgs {
+            hot_patch: Some(hot_patch),
+            ..
+        }) = self
+        {
+            context.config.server.hot_patch = *hot_patch;
+        }
+    }
+}
+
 #[derive(Debug, Args)]
 pub struct ServeCsrArgs {
     /// Open browser on startup
     #[arg(long, default_missing_value = "true", num_args = 0..=1)]
     pub open: Option<bool>,
+    /// Specify hostname, `--host` without a value listens on all addresses
+    #[arg(long, default_missing_value = "0.0.0.0", num_args = 0..=1)]
+    pub host: Option<String>,
+    /// Specify port
+    #[arg(long)]
+    pub port: Option<u32>,
+    /// Hot-patch changed Rust code instead of rebuilding and reloading the page
+    #[arg(long, default_missing_value = "true", num_args = 0..=1)]
+    pub hot_patch: Option<bool>,
 }
 
 #[derive(Debug, Args)]
@@ -89,6 +151,27 @@ pub struct ServeSsrArgs {
     /// Open browser on startup
     #[arg(long, default_missing_value = "true", num_args = 0..=1)]
     pub open: Option<bool>,
+    /// Specify hostname, `--host` without a value listens on all addresses
+    #[arg(long, default_missing_value = "0.0.0.0", num_args = 0..=1)]
+    pub host: Option<String>,
+    /// Specify port
+    #[arg(long)]
+    pub port: Option<u32>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PreviewCommands {
+    /// Client-side rendering
+    Csr(PreviewArgs),
+    /// Server-side Rendering
+    Ssr(PreviewArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct PreviewArgs {
+    /// Open browser on startup
+    #[arg(long, default_missing_value = "true", num_args = 0..=1)]
+    pub open: Option<bool>,
 }
 
 async fn build(
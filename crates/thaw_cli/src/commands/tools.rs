@@ -0,0 +1,77 @@
+use crate::{
+    config::WasmOptConfig,
+    utils::{
+        thaw_cli_cache_dir,
+        wasm_opt::{DEFAULT_BINARYEN_VERSION, binaryen_dir, install_binaryen},
+    },
+};
+use clap::Subcommand;
+use crossterm::style::Stylize;
+use tokio::fs;
+
+#[derive(Debug, Subcommand)]
+pub enum ToolsCommands {
+    /// Install the binaryen release set by `build.wasm_opt.version`
+    Install,
+    /// List the installed tools
+    List,
+    /// Remove the installed tools and the download cache
+    Clean,
+}
+
+impl ToolsCommands {
+    /// Only needs the config, so that it also works outside of a cargo project.
+    pub async fn run(&self, config: &WasmOptConfig) -> color_eyre::Result<()> {
+        match self {
+            Self::Install => {
+                let path = install_binaryen(config).await?;
+                println!("{} {}", "installed".green(), path.display());
+            }
+            Self::List => {
+                let configured = config
+                    .version
+                    .as_deref()
+                    .unwrap_or(DEFAULT_BINARYEN_VERSION);
+                let dir = binaryen_dir();
+                let mut versions = vec![];
+                if fs::try_exists(&dir).await? {
+                    let mut entries = fs::read_dir(&dir).await?;
+                    while let Some(entry) = entries.next_entry().await? {
+                        let version = entry.file_name().to_string_lossy().to_string();
+                        // Skips unfinished installations.
+                        if !version.starts_with('.') {
+                            versions.push(version);
+                        }
+                    }
+                }
+                versions.sort();
+                for version in versions {
+                    let marker = if version == configured {
+                        " (configured)".green().to_string()
+                    } else {
+                        String::new()
+                    };
+                    println!(
+                        "binaryen {version}{marker} {}",
+                        dir.join(&version).display().to_string().dark_grey()
+                    );
+                }
+                if let Ok(path) = which::which("wasm-opt") {
+                    println!(
+                        "wasm-opt on PATH {}",
+                        path.display().to_string().dark_grey()
+                    );
+                }
+            }
+            Self::Clean => {
+                for dir in [binaryen_dir(), thaw_cli_cache_dir()] {
+                    if fs::try_exists(&dir).await? {
+                        fs::remove_dir_all(&dir).await?;
+                        println!("{} {}", "removed".green(), dir.display());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
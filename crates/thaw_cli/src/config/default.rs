@@ -18,9 +18,21 @@ pub mod server {
     pub fn default_erase_components() -> bool {
         false
     }
+
+    pub fn default_site_addr() -> String {
+        "127.0.0.1:3000".to_string()
+    }
+}
+
+pub mod preview {
+    pub fn default_port() -> u32 {
+        4173
+    }
 }
 
 pub mod build {
+    use crate::config::CompressAlgorithm;
+
     pub fn default_out_dir() -> String {
         "dist".to_string()
     }
@@ -32,4 +44,36 @@ pub mod build {
     pub fn default_assets_manganis() -> bool {
         true
     }
+
+    pub fn default_hash_filenames() -> bool {
+        false
+    }
+
+    pub fn default_compress_algorithms() -> Vec<CompressAlgorithm> {
+        vec![
+            CompressAlgorithm::Br,
+            CompressAlgorithm::Gzip,
+            CompressAlgorithm::Zstd,
+        ]
+    }
+
+    pub fn default_compress_threshold() -> u64 {
+        1024
+    }
+
+    pub fn default_wasm_bindgen_demangle() -> bool {
+        true
+    }
+
+    pub fn default_wasm_bindgen_omit_default_module_path() -> bool {
+        true
+    }
+
+    pub fn default_wasm_opt_debuginfo() -> bool {
+        true
+    }
+
+    pub fn default_prerender_routes() -> Vec<String> {
+        vec!["/".to_string()]
+    }
 }
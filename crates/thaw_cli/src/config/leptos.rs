@@ -0,0 +1,127 @@
+//! `[package.metadata.leptos]` of cargo-leptos projects, read as defaults beneath `Thaw.toml`.
+
+use std::path::Path;
+use toml::{Table, Value};
+
+/// cargo-leptos keys and the `Thaw.toml` keys they set.
+const MAPPINGS: [(&str, &[&str]); 14] = [
+    ("output-name", &["build", "output_name"]),
+    ("site-pkg-dir", &["build", "assets_dir"]),
+    ("assets-dir", &["public_dir"]),
+    ("site-addr", &["server", "site_addr"]),
+    ("lib-features", &["build", "client", "features"]),
+    ("lib-profile-release", &["build", "client", "profile"]),
+    ("bin-features", &["build", "server", "features"]),
+    ("bin-profile-release", &["build", "server", "profile"]),
+    ("bin-target-triple", &["build", "server", "target"]),
+    ("lib-cargo-args", &["build", "client", "args"]),
+    ("bin-cargo-args", &["build", "server", "args"]),
+    ("lib-package", &["build", "client", "package"]),
+    ("bin-package", &["build", "server", "package"]),
+    ("bin-target", &["build", "server", "bin"]),
+];
+
+/// `*-default-features = false` becomes `no_default_features = true`.
+const DEFAULT_FEATURES_MAPPINGS: [(&str, &[&str]); 2] = [
+    (
+        "lib-default-features",
+        &["build", "client", "no_default_features"],
+    ),
+    (
+        "bin-default-features",
+        &["build", "server", "no_default_features"],
+    ),
+];
+
+/// Returns the leptos metadata of the manifest in the shape of `Thaw.toml`,
+/// or an empty table when there is none.
+pub fn leptos_metadata_config(cargo_toml_path: &Path) -> color_eyre::Result<Table> {
+    let Ok(content) = std::fs::read_to_string(cargo_toml_path) else {
+        return Ok(Table::new());
+    };
+    let manifest: Table = toml::from_str(&content)?;
+    let metadata = manifest
+        .get("package")
+        .and_then(|package| package.get("metadata"))
+        .and_then(|metadata| metadata.get("leptos"))
+        .and_then(Value::as_table);
+    Ok(metadata.map(from_leptos_metadata).unwrap_or_default())
+}
+
+fn from_leptos_metadata(metadata: &Table) -> Table {
+    let mut config = Table::new();
+    for (key, path) in MAPPINGS {
+        if let Some(value) = metadata.get(key) {
+            insert(&mut config, path, value.clone());
+        }
+    }
+    for (key, path) in DEFAULT_FEATURES_MAPPINGS {
+        if let Some(Value::Boolean(default_features)) = metadata.get(key) {
+            insert(&mut config, path, Value::Boolean(!default_features));
+        }
+    }
+    config
+}
+
+fn insert(table: &mut Table, path: &[&str], value: Value) {
+    let (key, parents) = path.split_last().unwrap();
+    let mut table = table;
+    for parent in parents {
+        table = table
+            .entry(*parent)
+            .or_insert_with(|| Value::Table(Table::new()))
+            .as_table_mut()
+            .unwrap();
+    }
+    table.insert(key.to_string(), value);
+}
+
+/// Recursively merges `over` into `base`, values of `over` win.
+pub fn merge(base: &mut Table, over: Table) {
+    for (key, value) in over {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(over)) => merge(base, over),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_from_leptos_metadata() {
+    let metadata: Table = toml::from_str(
+        r#"
+        output-name = "start-axum"
+        site-pkg-dir = "pkg"
+        site-addr = "127.0.0.1:3000"
+        lib-features = ["hydrate"]
+        lib-default-features = false
+        lib-profile-release = "wasm-release"
+        "#,
+    )
+    .unwrap();
+    let mut config = from_leptos_metadata(&metadata);
+    merge(
+        &mut config,
+        toml::from_str("[build]\nassets_dir = \"assets\"").unwrap(),
+    );
+
+    let expected: Table = toml::from_str(
+        r#"
+        [server]
+        site_addr = "127.0.0.1:3000"
+
+        [build]
+        output_name = "start-axum"
+        assets_dir = "assets"
+
+        [build.client]
+        features = ["hydrate"]
+        no_default_features = true
+        profile = "wasm-release"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(config, expected);
+}
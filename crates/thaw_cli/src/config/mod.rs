@@ -1,11 +1,12 @@
 mod default;
+mod leptos;
 
-use default::{build, default_public_dir, server};
+use default::{build, default_public_dir, preview, server};
 use serde::{
-    Deserialize, Deserializer,
+    Deserialize, Deserializer, Serialize,
     de::{self, Unexpected},
 };
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Deserialize)]
 pub struct Config {
@@ -31,6 +32,10 @@ pub struct Config {
     #[serde(default = "BuildConfig::default")]
     pub build: BuildConfig,
 
+    /// Preview server configuration.
+    #[serde(default = "PreviewConfig::default")]
+    pub preview: PreviewConfig,
+
     /// The directory from which .env files are loaded.
     /// Can be an absolute path, or a path relative to the project root.
     /// false will disable the .env file loading.
@@ -38,16 +43,31 @@ pub struct Config {
     /// Default: Root directory
     #[serde(default = "EnvDir::default")]
     pub env_dir: EnvDir,
+
+    /// External commands run at defined stages of `thaw build`.
+    #[serde(default = "Default::default")]
+    pub plugins: Vec<CommandPlugin>,
+
+    /// Apps of a cargo workspace, selected with `--package <name>`.
+    #[serde(default = "Default::default")]
+    pub apps: Vec<AppConfig>,
 }
 
 impl Config {
-    pub fn parse(path: PathBuf, user_input: bool) -> color_eyre::Result<Self> {
+    /// Parses `Thaw.toml`, on top of the `[package.metadata.leptos]` of cargo-leptos projects.
+    pub fn parse(
+        path: PathBuf,
+        user_input: bool,
+        cargo_toml_path: &Path,
+    ) -> color_eyre::Result<Self> {
         let config = if user_input {
             std::fs::read_to_string(path)?
         } else {
             std::fs::read_to_string(path).unwrap_or_default()
         };
-        let config: Self = toml::from_str(&config)?;
+        let mut table = leptos::leptos_metadata_config(cargo_toml_path)?;
+        leptos::merge(&mut table, toml::from_str(&config)?);
+        let config: Self = toml::Value::Table(table).try_into()?;
         Ok(config)
     }
 }
@@ -101,12 +121,26 @@ pub struct ServerConfig {
     #[serde(default = "server::default_port")]
     pub port: u32,
 
+    /// Set to true to exit if port is already in use,
+    /// instead of automatically trying the next available port.
+    ///
+    /// Default: false
+    #[serde(default = "Default::default")]
+    pub strict_port: bool,
+
     /// Automatically open the app in the browser on server start.
     ///
     /// Default: false
     #[serde(default = "server::default_open")]
     pub open: bool,
 
+    /// Enable TLS. `true` generates and caches a self-signed certificate,
+    /// or use `{ cert = "cert.pem", key = "key.pem" }` to provide your own.
+    ///
+    /// Default: false
+    #[serde(default = "Https::default")]
+    pub https: Https,
+
     /// Configure custom proxy rules for the dev server.
     #[serde(default = "Default::default")]
     pub proxy: Vec<Proxy>,
@@ -125,6 +159,20 @@ pub struct ServerConfig {
     /// Default: false
     #[serde(default = "server::default_erase_components")]
     pub erase_components: bool,
+
+    /// The address the Leptos server listens on, passed as `LEPTOS_SITE_ADDR`.
+    /// The dev server of `thaw serve ssr` proxies to it.
+    ///
+    /// Default: "127.0.0.1:3000"
+    #[serde(default = "server::default_site_addr")]
+    pub site_addr: String,
+
+    /// Hot-patch changed Rust code into the running app in `thaw serve csr`,
+    /// keeping its state. Falls back to a full rebuild when a patch cannot be made.
+    ///
+    /// Default: false
+    #[serde(default = "Default::default")]
+    pub hot_patch: bool,
 }
 
 impl Default for ServerConfig {
@@ -132,10 +180,83 @@ impl Default for ServerConfig {
         Self {
             host: server::default_host(),
             port: server::default_port(),
+            strict_port: false,
             open: server::default_open(),
+            https: Https::default(),
             proxy: Default::default(),
             watch: Watch::default(),
             erase_components: server::default_erase_components(),
+            site_addr: server::default_site_addr(),
+            hot_patch: false,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PreviewConfig {
+    /// Specify which IP addresses the preview server should listen on.
+    ///
+    /// Default: "localhost"
+    #[serde(default = "server::default_host")]
+    pub host: String,
+
+    /// Specify preview server port.
+    ///
+    /// Default: 4173
+    #[serde(default = "preview::default_port")]
+    pub port: u32,
+
+    /// Automatically open the app in the browser on preview server start.
+    ///
+    /// Default: false
+    #[serde(default = "server::default_open")]
+    pub open: bool,
+}
+
+impl Default for PreviewConfig {
+    fn default() -> Self {
+        Self {
+            host: server::default_host(),
+            port: preview::default_port(),
+            open: server::default_open(),
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub enum Https {
+    #[default]
+    False,
+    SelfSigned,
+    Pem {
+        cert: PathBuf,
+        key: PathBuf,
+    },
+}
+
+impl Https {
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        !matches!(self, Self::False)
+    }
+}
+
+impl<'de> Deserialize<'de> for Https {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum RawInput {
+            Bool(bool),
+            Pem { cert: PathBuf, key: PathBuf },
+        }
+
+        match RawInput::deserialize(deserializer)? {
+            RawInput::Bool(false) => Ok(Self::False),
+            RawInput::Bool(true) => Ok(Self::SelfSigned),
+            RawInput::Pem { cert, key } => Ok(Self::Pem { cert, key }),
         }
     }
 }
@@ -164,6 +285,48 @@ pub struct WatchPath {
     pub path: String,
 }
 
+#[derive(Debug, Deserialize, Clone)]
+pub struct CommandPlugin {
+    /// The name of the plugin, used in logs and error messages.
+    pub name: String,
+
+    /// Shell command to run in the project root directory.
+    /// A JSON description of the build is written to its stdin.
+    pub command: String,
+
+    /// The build stage at which the command is run.
+    pub hook: CommandPluginHook,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CommandPluginHook {
+    /// Before cargo build.
+    BuildStart,
+    /// After wasm-bindgen and wasm-opt generated the JS/WASM files.
+    PostWasmBindgen,
+    /// After all build artifacts have been written to the out_dir.
+    PostBuild,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AppConfig {
+    /// The name selecting the app with `--package`.
+    pub name: String,
+
+    /// The package built for the client.
+    ///
+    /// Default: name
+    #[serde(default)]
+    pub package: Option<String>,
+
+    /// The package of the SSR server binary.
+    ///
+    /// Default: package
+    #[serde(default)]
+    pub server_package: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct BuildConfig {
     /// Specify the output directory (relative to project root).
@@ -178,11 +341,56 @@ pub struct BuildConfig {
     #[serde(default = "build::default_assets_dir")]
     pub assets_dir: String,
 
+    /// The file name of the main JS/WASM bundle, without extension.
+    ///
+    /// Default: the package name
+    #[serde(default)]
+    pub output_name: Option<String>,
+
     /// Whether to enable manganis to collect assets from dependencies.
     ///
     /// Default: false
     #[serde(default = "build::default_assets_manganis")]
     pub assets_manganis: bool,
+
+    /// Add a content hash to the file names of the main JS/WASM bundle,
+    /// so that they can be cached forever. Not applied by the dev server.
+    ///
+    /// Default: false
+    #[serde(default = "build::default_hash_filenames")]
+    pub hash_filenames: bool,
+
+    /// Write precompressed `.br`, `.gz` and `.zst` siblings of text and WASM files.
+    /// `true` enables all algorithms, or use `{ algorithms = ["br", "gzip"], threshold = 1024 }`.
+    ///
+    /// Default: false
+    #[serde(default = "Compress::default")]
+    pub compress: Compress,
+
+    /// Cargo options of the WASM build.
+    #[serde(default = "CargoBuildConfig::default")]
+    pub client: CargoBuildConfig,
+
+    /// Cargo options of the SSR server build.
+    #[serde(default = "CargoBuildConfig::default")]
+    pub server: CargoBuildConfig,
+
+    /// wasm-bindgen settings for the main WASM bundle.
+    #[serde(default = "WasmBindgenConfig::default")]
+    pub wasm_bindgen: WasmBindgenConfig,
+
+    /// wasm-opt settings for the main WASM bundle.
+    #[serde(default = "WasmOptConfig::default")]
+    pub wasm_opt: WasmOptConfig,
+
+    /// Size limits in bytes checked after `thaw build`, the build fails when one is exceeded.
+    /// e.g. `{ wasm = 2_000_000, measure = "gzip" }`
+    #[serde(default = "SizeBudget::default")]
+    pub size_budget: SizeBudget,
+
+    /// Static site generation options for `thaw build ssg`.
+    #[serde(default = "PrerenderConfig::default")]
+    pub prerender: PrerenderConfig,
 }
 
 impl Default for BuildConfig {
@@ -190,7 +398,530 @@ impl Default for BuildConfig {
         Self {
             out_dir: build::default_out_dir(),
             assets_dir: build::default_assets_dir(),
+            output_name: None,
             assets_manganis: build::default_assets_manganis(),
+            hash_filenames: build::default_hash_filenames(),
+            compress: Compress::default(),
+            client: CargoBuildConfig::default(),
+            server: CargoBuildConfig::default(),
+            wasm_bindgen: WasmBindgenConfig::default(),
+            wasm_opt: WasmOptConfig::default(),
+            size_budget: SizeBudget::default(),
+            prerender: PrerenderConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub enum Compress {
+    #[default]
+    False,
+    Enabled {
+        algorithms: Vec<CompressAlgorithm>,
+        /// Files smaller than this size in bytes are not compressed.
+        threshold: u64,
+    },
+}
+
+impl<'de> Deserialize<'de> for Compress {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum RawInput {
+            Bool(bool),
+            Options {
+                #[serde(default = "build::default_compress_algorithms")]
+                algorithms: Vec<CompressAlgorithm>,
+                #[serde(default = "build::default_compress_threshold")]
+                threshold: u64,
+            },
+        }
+
+        match RawInput::deserialize(deserializer)? {
+            RawInput::Bool(false) => Ok(Self::False),
+            RawInput::Bool(true) => Ok(Self::Enabled {
+                algorithms: build::default_compress_algorithms(),
+                threshold: build::default_compress_threshold(),
+            }),
+            RawInput::Options {
+                algorithms,
+                threshold,
+            } => Ok(Self::Enabled {
+                algorithms,
+                threshold,
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressAlgorithm {
+    Br,
+    Gzip,
+    Zstd,
+}
+
+impl CompressAlgorithm {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Br => "br",
+            Self::Gzip => "gz",
+            Self::Zstd => "zst",
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq)]
+pub struct CargoBuildConfig {
+    /// The workspace package to build, `[[apps]]` and `--package` take precedence.
+    ///
+    /// Default: the package of the root manifest, and the client package for the server
+    #[serde(default)]
+    pub package: Option<String>,
+
+    /// The `[[bin]]` target of the server package run as the SSR executable.
+    /// Only used by `[build.server]`.
+    ///
+    /// Default: the only binary of the package, or the package name
+    #[serde(default)]
+    pub bin: Option<String>,
+
+    /// Cargo features to enable.
+    ///
+    /// Default: ["hydrate"] and ["ssr"] for SSR, ["csr"] for CSR if the package has it
+    #[serde(default)]
+    pub features: Option<Vec<String>>,
+
+    /// Do not enable the default features of the package.
+    ///
+    /// Default: false
+    #[serde(default)]
+    pub no_default_features: bool,
+
+    /// Cargo profile of release builds, e.g. "wasm-release".
+    ///
+    /// Default: "release"
+    #[serde(default)]
+    pub profile: Option<String>,
+
+    /// Target triple, e.g. "x86_64-unknown-linux-musl" for the server.
+    ///
+    /// Default: "wasm32-unknown-unknown" for the client, the host for the server
+    #[serde(default)]
+    pub target: Option<String>,
+
+    /// Flags passed to rustc through `RUSTFLAGS`.
+    ///
+    /// Default: []
+    #[serde(default)]
+    pub rustflags: Vec<String>,
+
+    /// Extra arguments passed to `cargo build`.
+    ///
+    /// Default: []
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+impl CargoBuildConfig {
+    /// The client target triple.
+    pub fn client_target(&self) -> &str {
+        self.target.as_deref().unwrap_or("wasm32-unknown-unknown")
+    }
+
+    /// The directory name of the profile in the cargo target directory.
+    pub fn profile_dir(&self, release: bool) -> &str {
+        match (release, &self.profile) {
+            (false, _) => "debug",
+            (true, Some(profile)) => profile,
+            (true, None) => "release",
+        }
+    }
+}
+
+/// Unknown fields are rejected, so that removed options like `weak_refs` are reported.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WasmBindgenConfig {
+    /// The JS output: "web" for an ES module, "no-modules" for a classic script
+    /// defining a global `wasm_bindgen`, or "bundler" for JS bundlers, which is not
+    /// injected into index.html. SSR only supports "web".
+    ///
+    /// Default: "web"
+    #[serde(default)]
+    pub target: WasmBindgenTarget,
+
+    /// Generate TypeScript `.d.ts` files next to the JS.
+    ///
+    /// Default: false
+    #[serde(default)]
+    pub typescript: bool,
+
+    /// Include extra debug checks in the generated JS.
+    ///
+    /// Default: false
+    #[serde(default)]
+    pub debug: bool,
+
+    /// Keep the DWARF debug sections in the WASM.
+    ///
+    /// Default: false
+    #[serde(default)]
+    pub keep_debug: bool,
+
+    /// Demangle the Rust symbol names in the name section.
+    ///
+    /// Default: true
+    #[serde(default = "build::default_wasm_bindgen_demangle")]
+    pub demangle: bool,
+
+    /// Leave out the `import.meta.url` based default path of the WASM file,
+    /// the injected init script always passes the path.
+    ///
+    /// Default: true
+    #[serde(default = "build::default_wasm_bindgen_omit_default_module_path")]
+    pub omit_default_module_path: bool,
+
+    /// Pass JS values as externref. Also enabled when the WASM is built with
+    /// `-Ctarget-feature=+reference-types`.
+    ///
+    /// Default: false
+    #[serde(default)]
+    pub reference_types: bool,
+}
+
+impl Default for WasmBindgenConfig {
+    fn default() -> Self {
+        Self {
+            target: WasmBindgenTarget::default(),
+            typescript: false,
+            debug: false,
+            keep_debug: false,
+            demangle: build::default_wasm_bindgen_demangle(),
+            omit_default_module_path: build::default_wasm_bindgen_omit_default_module_path(),
+            reference_types: false,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum WasmBindgenTarget {
+    #[default]
+    Web,
+    NoModules,
+    Bundler,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WasmOptConfig {
+    /// Optimization level: 0, 1, 2, 3, "s", "z", or false to skip wasm-opt.
+    ///
+    /// Default: "z", skipped by `thaw serve` unless `release` is set
+    #[serde(default)]
+    pub level: Option<WasmOptLevel>,
+
+    /// WASM features to enable in addition to reference-types, bulk-memory,
+    /// mutable-globals and nontrapping-float-to-int, e.g. `["simd"]`.
+    ///
+    /// Default: []
+    #[serde(default)]
+    pub features: Vec<String>,
+
+    /// Keep the name section and DWARF, `thaw analyze` reads the function names from it.
+    ///
+    /// Default: true
+    #[serde(default = "build::default_wasm_opt_debuginfo")]
+    pub debuginfo: bool,
+
+    /// Extra arguments passed to wasm-opt.
+    ///
+    /// Default: []
+    #[serde(default)]
+    pub args: Vec<String>,
+
+    /// Binaryen release to install, e.g. "version_123". When set,
+    /// wasm-opt on PATH is ignored so that every machine uses the same version.
+    ///
+    /// Default: "version_123" if wasm-opt is not on PATH
+    #[serde(default)]
+    pub version: Option<String>,
+
+    /// Expected SHA-256 of the binaryen archive. By default the `.sha256` file
+    /// published with the release, or next to the archive, is used. The
+    /// installation fails when neither is available.
+    #[serde(default)]
+    pub sha256: Option<String>,
+
+    /// Directory or URL to download the binaryen archive from instead of GitHub,
+    /// containing files named like the release assets.
+    #[serde(default)]
+    pub mirror: Option<String>,
+
+    /// Path to an already downloaded binaryen archive.
+    #[serde(default)]
+    pub archive: Option<PathBuf>,
+}
+
+impl Default for WasmOptConfig {
+    fn default() -> Self {
+        Self {
+            level: None,
+            features: Vec::new(),
+            debuginfo: build::default_wasm_opt_debuginfo(),
+            args: Vec::new(),
+            version: None,
+            sha256: None,
+            mirror: None,
+            archive: None,
         }
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WasmOptLevel {
+    False,
+    O0,
+    O1,
+    O2,
+    O3,
+    Os,
+    Oz,
+}
+
+impl WasmOptLevel {
+    /// The wasm-opt argument, `None` when wasm-opt is skipped.
+    pub fn arg(&self) -> Option<&'static str> {
+        match self {
+            Self::False => None,
+            Self::O0 => Some("-O0"),
+            Self::O1 => Some("-O1"),
+            Self::O2 => Some("-O2"),
+            Self::O3 => Some("-O3"),
+            Self::Os => Some("-Os"),
+            Self::Oz => Some("-Oz"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for WasmOptLevel {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum RawInput {
+            Number(u64),
+            String(String),
+            Bool(bool),
+        }
+
+        let expected = &"0, 1, 2, 3, \"s\", \"z\" or false";
+        match RawInput::deserialize(deserializer)? {
+            RawInput::Number(0) => Ok(Self::O0),
+            RawInput::Number(1) => Ok(Self::O1),
+            RawInput::Number(2) => Ok(Self::O2),
+            RawInput::Number(3) => Ok(Self::O3),
+            RawInput::Number(n) => Err(de::Error::invalid_value(Unexpected::Unsigned(n), expected)),
+            RawInput::String(s) => match s.as_str() {
+                "0" => Ok(Self::O0),
+                "1" => Ok(Self::O1),
+                "2" => Ok(Self::O2),
+                "3" => Ok(Self::O3),
+                "s" => Ok(Self::Os),
+                "z" => Ok(Self::Oz),
+                _ => Err(de::Error::invalid_value(Unexpected::Str(&s), expected)),
+            },
+            RawInput::Bool(false) => Ok(Self::False),
+            RawInput::Bool(true) => Err(de::Error::invalid_value(Unexpected::Bool(true), expected)),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq)]
+pub struct SizeBudget {
+    /// Maximum size of the main WASM file.
+    pub wasm: Option<u64>,
+    /// Maximum size of the main JS file.
+    pub js: Option<u64>,
+    /// Maximum size of each collected asset.
+    pub asset: Option<u64>,
+    /// Maximum size of the main bundle and the assets together.
+    pub total: Option<u64>,
+    /// Which size the limits apply to.
+    ///
+    /// Default: "raw"
+    #[serde(default)]
+    pub measure: SizeMeasure,
+}
+
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SizeMeasure {
+    #[default]
+    Raw,
+    Gzip,
+    Brotli,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PrerenderConfig {
+    /// Routes to prerender into `<route>/index.html`.
+    ///
+    /// Default: ["/"]
+    #[serde(default = "build::default_prerender_routes")]
+    pub routes: Vec<String>,
+
+    /// Whether to follow same-origin links found in the prerendered pages.
+    ///
+    /// Default: false
+    #[serde(default = "Default::default")]
+    pub crawl_links: bool,
+}
+
+impl Default for PrerenderConfig {
+    fn default() -> Self {
+        Self {
+            routes: build::default_prerender_routes(),
+            crawl_links: false,
+        }
+    }
+}
+
+#[test]
+fn test_command_plugins() {
+    let config: Config = toml::from_str(
+        r#"
+        [[plugins]]
+        name = "sitemap"
+        command = "node scripts/sitemap.js"
+        hook = "post-build"
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(config.plugins.len(), 1);
+    assert_eq!(config.plugins[0].name, "sitemap");
+    assert_eq!(config.plugins[0].hook, CommandPluginHook::PostBuild);
+}
+
+#[test]
+fn test_https() {
+    let config: Config = toml::from_str("[server]\nhttps = true").unwrap();
+    assert_eq!(config.server.https, Https::SelfSigned);
+
+    let config: Config =
+        toml::from_str("[server]\nhttps = { cert = \"cert.pem\", key = \"key.pem\" }").unwrap();
+    assert_eq!(
+        config.server.https,
+        Https::Pem {
+            cert: PathBuf::from("cert.pem"),
+            key: PathBuf::from("key.pem"),
+        }
+    );
+    let config: Config = toml::from_str("").unwrap();
+    assert!(!config.server.https.is_enabled());
+}
+
+#[test]
+fn test_compress() {
+    let config: Config = toml::from_str("[build]\ncompress = true").unwrap();
+    assert_eq!(
+        config.build.compress,
+        Compress::Enabled {
+            algorithms: vec![
+                CompressAlgorithm::Br,
+                CompressAlgorithm::Gzip,
+                CompressAlgorithm::Zstd
+            ],
+            threshold: 1024,
+        }
+    );
+
+    let config: Config = toml::from_str("[build]\ncompress = { algorithms = [\"br\"] }").unwrap();
+    assert_eq!(
+        config.build.compress,
+        Compress::Enabled {
+            algorithms: vec![CompressAlgorithm::Br],
+            threshold: 1024,
+        }
+    );
+}
+
+#[test]
+fn test_size_budget() {
+    let config: Config =
+        toml::from_str("[build.size_budget]\nwasm = 2_000_000\nmeasure = \"gzip\"").unwrap();
+    assert_eq!(
+        config.build.size_budget,
+        SizeBudget {
+            wasm: Some(2_000_000),
+            measure: SizeMeasure::Gzip,
+            ..Default::default()
+        }
+    );
+    assert_eq!(BuildConfig::default().size_budget.measure, SizeMeasure::Raw);
+}
+
+#[test]
+fn test_wasm_opt_level() {
+    let config: Config = toml::from_str("[build.wasm_opt]\nlevel = \"s\"").unwrap();
+    assert_eq!(config.build.wasm_opt.level, Some(WasmOptLevel::Os));
+    assert!(config.build.wasm_opt.debuginfo);
+
+    let config: Config = toml::from_str("[build.wasm_opt]\nlevel = 2").unwrap();
+    assert_eq!(config.build.wasm_opt.level, Some(WasmOptLevel::O2));
+
+    let config: Config = toml::from_str("[build.wasm_opt]\nlevel = false").unwrap();
+    assert_eq!(config.build.wasm_opt.level.unwrap().arg(), None);
+
+    assert!(toml::from_str::<Config>("[build.wasm_opt]\nlevel = 4").is_err());
+}
+
+#[test]
+fn test_wasm_bindgen() {
+    let config: Config =
+        toml::from_str("[build.wasm_bindgen]\ntarget = \"no-modules\"\ntypescript = true").unwrap();
+    let wasm_bindgen = config.build.wasm_bindgen;
+    assert_eq!(wasm_bindgen.target, WasmBindgenTarget::NoModules);
+    assert!(wasm_bindgen.typescript);
+    assert!(wasm_bindgen.demangle);
+    assert!(wasm_bindgen.omit_default_module_path);
+
+    assert!(toml::from_str::<Config>("[build.wasm_bindgen]\nweak_refs = false").is_err());
+}
+
+#[test]
+fn test_cargo_build() {
+    let config: Config = toml::from_str(
+        r#"
+        [build.server]
+        package = "server"
+        bin = "site-server"
+        target = "x86_64-unknown-linux-musl"
+        rustflags = ["-Ctarget-feature=+crt-static"]
+
+        [build.client]
+        profile = "wasm-release"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(
+        config.build.client.client_target(),
+        "wasm32-unknown-unknown"
+    );
+    assert_eq!(config.build.client.profile_dir(true), "wasm-release");
+    assert_eq!(config.build.client.profile_dir(false), "debug");
+    assert_eq!(
+        config.build.server.target.as_deref(),
+        Some("x86_64-unknown-linux-musl")
+    );
+    assert_eq!(config.build.server.package.as_deref(), Some("server"));
+    assert_eq!(config.build.server.bin.as_deref(), Some("site-server"));
+    assert_eq!(config.build.client.package, None);
+}
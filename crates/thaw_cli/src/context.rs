@@ -1,11 +1,13 @@
-use crate::{config::Config, env::Env, logger::Logger, utils::client::Client};
-use cargo_manifest::Manifest;
-use cargo_metadata::MetadataCommand;
-use color_eyre::eyre::eyre;
-use std::{
-    path::{Path, PathBuf},
-    sync::Arc,
+use crate::{
+    config::{AppConfig, Config},
+    env::Env,
+    logger::Logger,
+    plugins::PluginContainer,
+    utils::client::Client,
 };
+use cargo_metadata::{Metadata, MetadataCommand, Package, TargetKind};
+use color_eyre::eyre::eyre;
+use std::{path::PathBuf, sync::Arc};
 use tokio::time;
 
 #[derive(Debug)]
@@ -20,7 +22,11 @@ pub struct Context {
     pub(crate) wasm_bindgen_dir: PathBuf,
     pub(crate) out_dir: PathBuf,
     pub(crate) assets_dir: PathBuf,
-    cargo_manifest: Manifest,
+    /// The directory of the package built for the client.
+    pub(crate) package_dir: PathBuf,
+    package: Package,
+    /// The package of the SSR server binary, the client package unless set by an app.
+    server_package: Package,
     pub(crate) create_version: &'static str,
     pub(crate) serve: bool,
     pub(crate) ssr: bool,
@@ -29,6 +35,8 @@ pub struct Context {
     pub(crate) logger: Logger,
     pub(crate) init_start_time: time::Instant,
     pub(crate) client: Arc<Client>,
+    /// Built-in and user plugins.
+    pub(crate) plugins: PluginContainer,
 }
 
 impl Context {
@@ -39,12 +47,27 @@ impl Context {
         logger: Logger,
         init_start_time: time::Instant,
         serve: bool,
+        selected_package: Option<&str>,
     ) -> color_eyre::Result<Self> {
-        let cargo_manifest = Manifest::from_path(current_dir.join("Cargo.toml"))?;
-        let package_name = Self::package_name(&cargo_manifest, &current_dir)?;
-
         let metadata = MetadataCommand::new().exec()?;
-        let target_dir = metadata.target_directory.into_std_path_buf();
+        // `[package.metadata.leptos]` is read again from the manifest of the selected package,
+        // which is not the one in the current directory in a workspace.
+        let (package, _) = Self::resolve_packages(&metadata, &config, selected_package)?;
+        let config = Config::parse(
+            current_dir.join("Thaw.toml"),
+            false,
+            package.manifest_path.as_std_path(),
+        )?;
+        let (package, server_package) =
+            Self::resolve_packages(&metadata, &config, selected_package)?;
+        let package_name = package.name.to_string();
+        let package_dir = package
+            .manifest_path
+            .parent()
+            .map(|dir| dir.as_std_path().to_path_buf())
+            .unwrap_or_else(|| current_dir.clone());
+
+        let target_dir = metadata.target_directory.clone().into_std_path_buf();
         let thaw_cli_dir = target_dir.join("thaw-cli");
 
         let wasm_bindgen_dir = thaw_cli_dir
@@ -62,6 +85,7 @@ impl Context {
 
         let assets_dir = out_dir.join(&config.build.assets_dir);
         let open = config.server.open;
+        let plugins = PluginContainer::new(&config);
 
         Ok(Self {
             config,
@@ -71,7 +95,9 @@ impl Context {
             wasm_bindgen_dir,
             out_dir,
             assets_dir,
-            cargo_manifest,
+            package_dir,
+            package,
+            server_package,
             create_version: env!("CARGO_PKG_VERSION"),
             serve,
             ssr: false,
@@ -79,32 +105,171 @@ impl Context {
             logger,
             init_start_time,
             client: Arc::new(Client::new()),
+            plugins,
         })
     }
 
-    pub(crate) fn cargo_package_name(&self) -> color_eyre::Result<String> {
-        if let Some(package) = &self.cargo_manifest.package {
-            color_eyre::Result::Ok(package.name.clone())
-        } else {
-            color_eyre::Result::Err(eyre!("The Carog.toml file does not have a package name"))
+    pub(crate) fn cargo_package_name(&self) -> String {
+        self.package.name.to_string()
+    }
+
+    pub(crate) fn server_package_name(&self) -> String {
+        self.server_package.name.to_string()
+    }
+
+    /// The SSR executable: `build.server.bin`, the only `[[bin]]` of the server package,
+    /// or the package name.
+    pub(crate) fn server_bin_name(&self) -> String {
+        if let Some(bin) = &self.config.build.server.bin {
+            return bin.clone();
+        }
+        let mut bins = self
+            .server_package
+            .targets
+            .iter()
+            .filter(|target| target.is_kind(TargetKind::Bin));
+        match (bins.next(), bins.next()) {
+            (Some(bin), None) => bin.name.clone(),
+            _ => self.server_package_name(),
         }
     }
 
-    fn package_name(manifest: &Manifest, current_dir: &Path) -> color_eyre::Result<String> {
-        if let Some(package) = &manifest.package {
-            color_eyre::Result::Ok(package.name.clone())
-        } else if let Some(dir_name) = current_dir.file_name().and_then(|name| name.to_str()) {
-            color_eyre::Result::Ok(dir_name.to_string())
-        } else {
-            color_eyre::Result::Err(eyre!("The Carog.toml file does not have a package name"))
+    /// The file name of the WASM built by cargo, without extension.
+    pub(crate) fn wasm_artifact_name(&self) -> String {
+        let lib =
+            self.package.targets.iter().find(|target| {
+                target.is_kind(TargetKind::CDyLib) || target.is_kind(TargetKind::Lib)
+            });
+        match lib {
+            Some(lib) => lib.name.replace('-', "_"),
+            None => self.package.name.to_string(),
         }
     }
 
-    pub(crate) fn cargo_features_contains_key(&self, key: &str) -> bool {
-        if let Some(features) = &self.cargo_manifest.features {
-            features.contains_key(key)
-        } else {
-            false
+    /// The file name of the main JS/WASM bundle, without extension.
+    pub(crate) fn output_name(&self) -> color_eyre::Result<String> {
+        match &self.config.build.output_name {
+            Some(output_name) => Ok(output_name.clone()),
+            None => Ok(self.cargo_package_name()),
         }
     }
+
+    /// The `src` directories of the client and server packages, watched by the dev server.
+    pub(crate) fn src_dirs(&self) -> Vec<PathBuf> {
+        let mut dirs = vec![self.package_dir.join("src")];
+        if self.server_package.id != self.package.id
+            && let Some(dir) = self.server_package.manifest_path.parent()
+        {
+            dirs.push(dir.as_std_path().join("src"));
+        }
+        dirs
+    }
+
+    pub(crate) fn cargo_features_contains_key(&self, key: &str) -> bool {
+        self.package.features.contains_key(key)
+    }
+
+    /// Resolves the client package from `--package`, naming an app or a workspace member,
+    /// the only app, `build.client.package` or the root manifest, in that order.
+    /// The server package is set by the app or `build.server.package`, or is the client package.
+    fn resolve_packages(
+        metadata: &Metadata,
+        config: &Config,
+        selected_package: Option<&str>,
+    ) -> color_eyre::Result<(Package, Package)> {
+        let members = metadata.workspace_packages();
+        let find = |name: &str| {
+            members
+                .iter()
+                .find(|package| package.name.as_str() == name)
+                .map(|package| (*package).clone())
+                .ok_or_else(|| {
+                    eyre!(
+                        "Package `{name}` is not a member of the workspace. Members: {}",
+                        member_names(&members)
+                    )
+                })
+        };
+
+        let app = select_app(&config.apps, selected_package)?;
+        let package = match (app, selected_package) {
+            (Some(app), _) => find(app.package.as_deref().unwrap_or(&app.name))?,
+            (None, Some(name)) => find(name)?,
+            (None, None) => match (&config.build.client.package, metadata.root_package()) {
+                (Some(name), _) => find(name)?,
+                (None, Some(package)) => package.clone(),
+                (None, None) => {
+                    return Err(eyre!(
+                        "Cargo.toml is a virtual workspace manifest, select a package with `--package`, or add `[[apps]]` to Thaw.toml. Members: {}",
+                        member_names(&members)
+                    ));
+                }
+            },
+        };
+        let server_package = match app.and_then(|app| app.server_package.as_ref()).or(config
+            .build
+            .server
+            .package
+            .as_ref())
+        {
+            Some(name) => find(name)?,
+            None => package.clone(),
+        };
+        Ok((package, server_package))
+    }
+}
+
+fn member_names(members: &[&Package]) -> String {
+    members
+        .iter()
+        .map(|package| package.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The app named by `--package`, or the only app when none is named.
+fn select_app<'a>(
+    apps: &'a [AppConfig],
+    selected_package: Option<&str>,
+) -> color_eyre::Result<Option<&'a AppConfig>> {
+    match selected_package {
+        Some(name) => Ok(apps.iter().find(|app| app.name == name)),
+        None => match apps {
+            [] => Ok(None),
+            [app] => Ok(Some(app)),
+            apps => Err(eyre!(
+                "Thaw.toml has several apps, select one with `--package`: {}",
+                apps.iter()
+                    .map(|app| app.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        },
+    }
+}
+
+#[test]
+fn test_select_app() {
+    let app = |name: &str| AppConfig {
+        name: name.to_string(),
+        package: None,
+        server_package: None,
+    };
+    let apps = [app("admin"), app("shop")];
+
+    assert_eq!(
+        select_app(&apps, Some("shop"))
+            .unwrap()
+            .map(|app| app.name.as_str()),
+        Some("shop")
+    );
+    assert!(select_app(&apps, Some("server")).unwrap().is_none());
+    assert!(select_app(&apps, None).is_err());
+    assert_eq!(
+        select_app(&apps[..1], None)
+            .unwrap()
+            .map(|app| app.name.as_str()),
+        Some("admin")
+    );
+    assert!(select_app(&[], None).unwrap().is_none());
 }
@@ -45,6 +45,12 @@ pub enum Message {
     Build(String),
     InitBuildFinished,
     PageReload(Vec<PathBuf>, color_eyre::Result<()>),
+    HotUpdate(Vec<PathBuf>),
+    HotPatch(Vec<PathBuf>),
+    /// The rebuild did not change the output, so the page is not reloaded.
+    Unchanged(Vec<PathBuf>),
+    /// A multi-line table, printed as is.
+    SizeReport(String),
 }
 
 impl Message {
@@ -115,8 +121,31 @@ impl Message {
             | (
                 Self::Build(_),
                 Self::PageReload(_, _),
+            )
+            // Finished
+            // HotUpdate
+            | (
+                Self::Build(_),
+                Self::HotUpdate(_),
+            )
+            // Building hot patch
+            // HotPatch
+            | (
+                Self::Build(_),
+                Self::HotPatch(_),
+            )
+            // Finished
+            // Unchanged
+            | (
+                Self::Build(_),
+                Self::Unchanged(_),
+            )
+            // Measuring bundle sizes
+            // SizeReport
+            | (
+                Self::Build(_),
+                Self::SizeReport(_),
             )=> true,
-            (Self::CargoBuildFinished, Self::CargoBuildFinished) => unreachable!(),
             (_, _) => false,
         }
     }
@@ -142,7 +171,7 @@ pub enum CargoPackagingMessage {
     Warning(String),
     Error(String),
     Finished(String),
-    CompilerMessage(Diagnostic),
+    CompilerMessage(Box<Diagnostic>),
     Other(String),
 }
 
@@ -162,10 +191,32 @@ impl fmt::Display for CargoPackagingMessage {
     }
 }
 
+impl CargoPackagingMessage {
+    /// Prefixes the message with `[label]`, for the output of concurrent builds.
+    pub fn labeled(self, label: &str) -> Self {
+        let label = format!("[{label}]").dark_grey();
+        match self {
+            Self::CompilerMessage(mut value) => {
+                if let Some(rendered) = &value.rendered {
+                    value.rendered = Some(format!("{label} {rendered}"));
+                }
+                Self::CompilerMessage(value)
+            }
+            Self::Blocking(value) => Self::Blocking(format!("{label} {value}")),
+            Self::Downloaded(value) => Self::Downloaded(format!("{label} {value}")),
+            Self::Compiling(value) => Self::Compiling(format!("{label} {value}")),
+            Self::Warning(value) => Self::Warning(format!("{label} {value}")),
+            Self::Error(value) => Self::Error(format!("{label} {value}")),
+            Self::Finished(value) => Self::Finished(format!("{label} {value}")),
+            Self::Other(value) => Self::Other(format!("{label} {value}")),
+        }
+    }
+}
+
 impl From<String> for CargoPackagingMessage {
     fn from(value: String) -> Self {
         if let Ok(diagnostic) = serde_json::from_str::<Diagnostic>(&value) {
-            return Self::CompilerMessage(diagnostic);
+            return Self::CompilerMessage(Box::new(diagnostic));
         }
 
         let trim_start = value.trim_start();
@@ -195,7 +246,7 @@ impl From<String> for CargoPackagingMessage {
 
 impl From<Diagnostic> for CargoPackagingMessage {
     fn from(value: Diagnostic) -> Self {
-        Self::CompilerMessage(value)
+        Self::CompilerMessage(Box::new(value))
     }
 }
 
@@ -233,7 +284,24 @@ impl PrintMessage {
             Message::CargoPackaging(message) => {
                 self.stdout.execute(style::Print(message))?;
             }
-            Message::Build(message) => {
+            Message::Build(message) | Message::SizeReport(message) => {
+                self.stdout.execute(style::Print(message))?;
+            }
+            Message::PageReload(paths, build_result) if paths.is_empty() => {
+                let now = chrono::Local::now();
+                let message = match build_result {
+                    Ok(_) => format!(
+                        "{} {} {}",
+                        now.format("%H:%M:%S"),
+                        "[thaw-cli]".cyan(),
+                        "page reload".green(),
+                    ),
+                    Err(err) => format!(
+                        "{} {} error: {err:?}",
+                        now.format("%H:%M:%S"),
+                        "[thaw-cli]".red(),
+                    ),
+                };
                 self.stdout.execute(style::Print(message))?;
             }
             Message::PageReload(paths, build_result) => {
@@ -256,6 +324,24 @@ impl PrintMessage {
                     self.stdout.execute(style::Print(message))?;
                 }
             }
+            Message::HotUpdate(paths) | Message::HotPatch(paths) | Message::Unchanged(paths) => {
+                let label = match message {
+                    Message::HotPatch(_) => "hot patch",
+                    Message::Unchanged(_) => "no change",
+                    _ => "hmr update",
+                };
+                for path in paths {
+                    let now = chrono::Local::now();
+                    let message = format!(
+                        "{} {} {} {}",
+                        now.format("%H:%M:%S"),
+                        "[thaw-cli]".cyan(),
+                        label.green(),
+                        normalize_path(path.strip_prefix(&self.current_dir).unwrap_or(path))
+                    );
+                    self.stdout.execute(style::Print(message))?;
+                }
+            }
             Message::CargoBuildFinished => {}
             _ => unreachable!(),
         }
@@ -20,6 +20,10 @@ use tokio::time;
 async fn main() -> color_eyre::Result<()> {
     color_eyre::install()?;
 
+    if let Some(code) = build::hot_patch::run_rustc_wrapper()? {
+        std::process::exit(code);
+    }
+
     let init_start_time = time::Instant::now();
 
     let cli = Cli::parse();
@@ -27,7 +31,10 @@ async fn main() -> color_eyre::Result<()> {
     let current_dir = std::env::current_dir()?;
 
     let config_path = current_dir.join("Thaw.toml");
-    let config = Config::parse(config_path, false)?;
+    let config = Config::parse(config_path, false, &current_dir.join("Cargo.toml"))?;
+    if let Some(tools) = cli.tools() {
+        return tools.run(&config.build.wasm_opt).await;
+    }
     let env = Env::load(&current_dir, cli.mode(), &config.env_dir)?;
 
     let logger = Logger::new(current_dir.clone());
@@ -38,6 +45,7 @@ async fn main() -> color_eyre::Result<()> {
         logger,
         init_start_time,
         cli.is_serve(),
+        cli.package(),
     )?;
 
     cli.run(context).await
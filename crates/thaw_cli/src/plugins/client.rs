@@ -0,0 +1,55 @@
+use super::{
+    Plugin,
+    html::{HtmlTagDescriptor, HtmlTagInjectTo, IndexHtmlTransformResult},
+};
+use crate::{constants::CLIENT_PUBLIC_PATH, context::Context};
+use futures_util::future::BoxFuture;
+use std::collections::HashMap;
+use tokio::{fs, io::AsyncWriteExt};
+
+/// Injects the dev server client into `index.html` during `thaw serve`.
+#[derive(Debug)]
+pub struct ClientPlugin;
+
+impl Plugin for ClientPlugin {
+    fn name(&self) -> &str {
+        "thaw:client"
+    }
+
+    fn transform_index_html<'a>(
+        &'a self,
+        context: &'a Context,
+        _html: &'a str,
+    ) -> BoxFuture<'a, color_eyre::Result<Option<IndexHtmlTransformResult>>> {
+        Box::pin(async move {
+            if !context.serve {
+                return Ok(None);
+            }
+
+            let out_dir = &context.out_dir;
+            let path = out_dir.join(format!(".{CLIENT_PUBLIC_PATH}.js"));
+            if !fs::try_exists(&path).await? {
+                fs::create_dir_all(&path.parent().unwrap()).await?;
+                let mut file = fs::File::create_new(path).await?;
+                file.write_all(include_str!("../client/client.js").as_bytes())
+                    .await?;
+            }
+
+            Ok(Some(IndexHtmlTransformResult {
+                tags: vec![client_script_tag()],
+            }))
+        })
+    }
+}
+
+pub fn client_script_tag() -> HtmlTagDescriptor {
+    HtmlTagDescriptor {
+        tag: "script",
+        attrs: HashMap::from([
+            ("type", "module".to_string()),
+            ("src", format!("{CLIENT_PUBLIC_PATH}.js")),
+        ]),
+        children: None,
+        inject_to: HtmlTagInjectTo::HeadPrepend,
+    }
+}
@@ -0,0 +1,144 @@
+use super::Plugin;
+use crate::{
+    build::assets::BundledAsset,
+    config::{CommandPlugin as CommandPluginConfig, CommandPluginHook},
+    context::Context,
+    logger,
+};
+use color_eyre::eyre::eyre;
+use futures_util::future::BoxFuture;
+use serde::Serialize;
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process::Stdio,
+};
+use tokio::{io::AsyncWriteExt, process::Command};
+
+/// Runs an external command declared in `[[plugins]]` of `Thaw.toml`.
+#[derive(Debug)]
+pub struct CommandPlugin {
+    config: CommandPluginConfig,
+}
+
+/// The build description written to the stdin of the command.
+#[derive(Debug, Serialize)]
+struct BuildDescription<'a> {
+    hook: CommandPluginHook,
+    ssr: bool,
+    release: bool,
+    out_dir: &'a Path,
+    assets_dir: PathBuf,
+    assets: &'a [BundledAsset],
+}
+
+impl CommandPlugin {
+    pub fn new(config: CommandPluginConfig) -> Self {
+        Self { config }
+    }
+
+    async fn run(
+        &self,
+        context: &Context,
+        hook: CommandPluginHook,
+        assets: &[BundledAsset],
+    ) -> color_eyre::Result<()> {
+        // Command plugins are only part of `thaw build`.
+        if context.serve || self.config.hook != hook {
+            return Ok(());
+        }
+
+        context
+            .logger
+            .send(logger::Message::Build(format!(
+                "Running plugin {}",
+                self.config.name
+            )))
+            .await?;
+
+        let assets_dir = if context.ssr {
+            context
+                .out_dir
+                .join("client")
+                .join(&context.config.build.assets_dir)
+        } else {
+            context.assets_dir.clone()
+        };
+        let description = serde_json::to_vec(&BuildDescription {
+            hook,
+            ssr: context.ssr,
+            release: context.config.release,
+            out_dir: &context.out_dir,
+            assets_dir,
+            assets,
+        })?;
+
+        let mut cmd = if cfg!(windows) {
+            let mut cmd = Command::new("cmd");
+            cmd.arg("/C");
+            cmd
+        } else {
+            let mut cmd = Command::new("sh");
+            cmd.arg("-c");
+            cmd
+        };
+        cmd.arg(&self.config.command);
+        cmd.current_dir(&context.current_dir);
+        cmd.envs(context.env.cloned_into_iter());
+
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        // Written while the output is read, so neither pipe fills up and blocks the command.
+        let mut stdin = child.stdin.take().unwrap();
+        let write_stdin = tokio::spawn(async move {
+            match stdin.write_all(&description).await {
+                // The command exited without reading the description.
+                Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+                result => result,
+            }
+        });
+
+        let output = child.wait_with_output().await?;
+        write_stdin.await??;
+        if !output.status.success() {
+            return Err(eyre!(
+                "`{}` exited with {}\n{}{}",
+                self.config.command,
+                output.status,
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl Plugin for CommandPlugin {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn build_start<'a>(&'a self, context: &'a Context) -> BoxFuture<'a, color_eyre::Result<()>> {
+        Box::pin(self.run(context, CommandPluginHook::BuildStart, &[]))
+    }
+
+    fn after_wasm_bindgen<'a>(
+        &'a self,
+        context: &'a Context,
+        _out_dir: &'a Path,
+    ) -> BoxFuture<'a, color_eyre::Result<()>> {
+        Box::pin(self.run(context, CommandPluginHook::PostWasmBindgen, &[]))
+    }
+
+    fn write_bundle<'a>(
+        &'a self,
+        context: &'a Context,
+        assets: &'a [BundledAsset],
+    ) -> BoxFuture<'a, color_eyre::Result<()>> {
+        Box::pin(self.run(context, CommandPluginHook::PostBuild, assets))
+    }
+}
@@ -1,4 +1,3 @@
-use crate::{context::Context, server::middlewares::index_html::dev_html_hook};
 use regex::{Captures, Regex};
 use std::collections::HashMap;
 
@@ -234,64 +233,6 @@ pub fn apply_html_transform(mut html: String, tags: Vec<HtmlTagDescriptor>) -> S
     html
 }
 
-pub struct BuildHtml;
-
-impl BuildHtml {
-    pub async fn transform(context: &Context, html: String) -> color_eyre::Result<String> {
-        let mut res = Self::main_wasm_hook(context)?;
-
-        if context.serve {
-            res.push(dev_html_hook(context).await?);
-        }
-
-        let tags = res.into_iter().flat_map(|res| res.tags).collect::<Vec<_>>();
-
-        Ok(apply_html_transform(html, tags))
-    }
-
-    fn main_wasm_hook(context: &Context) -> color_eyre::Result<Vec<IndexHtmlTransformResult>> {
-        let package_name = context.cargo_package_name()?;
-        let assets_path = &context.config.build.assets_dir;
-        let js_url = format!("/{assets_path}/{package_name}.js");
-        let wasm_url = format!("/{assets_path}/{package_name}_bg.wasm");
-
-        let init_script =
-            format!("import init from '{js_url}';await init({{ module_or_path: '{wasm_url}' }})");
-
-        Ok(vec![
-            IndexHtmlTransformResult {
-                tags: vec![HtmlTagDescriptor {
-                    tag: "link",
-                    attrs: HashMap::from([("rel", "modulepreload".to_string()), ("href", js_url)]),
-                    children: None,
-                    inject_to: HtmlTagInjectTo::Head,
-                }],
-            },
-            IndexHtmlTransformResult {
-                tags: vec![HtmlTagDescriptor {
-                    tag: "link",
-                    attrs: HashMap::from([
-                        ("rel", "preload".to_string()),
-                        ("as", "fetch".to_string()),
-                        ("type", "application/wasm".to_string()),
-                        ("href", wasm_url),
-                    ]),
-                    children: None,
-                    inject_to: HtmlTagInjectTo::Head,
-                }],
-            },
-            IndexHtmlTransformResult {
-                tags: vec![HtmlTagDescriptor {
-                    tag: "script",
-                    attrs: HashMap::from([("type", "module".to_string())]),
-                    children: Some(init_script),
-                    inject_to: HtmlTagInjectTo::Body,
-                }],
-            },
-        ])
-    }
-}
-
 #[test]
 fn test_inject_to_head() {
     let html = r#"<html><head lang></head><body></body></html>"#.to_string();
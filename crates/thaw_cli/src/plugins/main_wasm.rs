@@ -0,0 +1,122 @@
+use super::{
+    Plugin,
+    html::{HtmlTagDescriptor, HtmlTagInjectTo, IndexHtmlTransformResult},
+};
+use crate::{
+    build::{hot_patch, wasm::main_bundle_names},
+    config::WasmBindgenTarget,
+    context::Context,
+};
+use futures_util::future::BoxFuture;
+use std::collections::HashMap;
+
+/// Preloads and initializes the main JS/WASM bundle in `index.html`.
+#[derive(Debug)]
+pub struct MainWasmPlugin;
+
+impl Plugin for MainWasmPlugin {
+    fn name(&self) -> &str {
+        "thaw:main-wasm"
+    }
+
+    fn transform_index_html<'a>(
+        &'a self,
+        context: &'a Context,
+        _html: &'a str,
+    ) -> BoxFuture<'a, color_eyre::Result<Option<IndexHtmlTransformResult>>> {
+        Box::pin(async move {
+            let target = context.config.build.wasm_bindgen.target;
+            if target == WasmBindgenTarget::Bundler {
+                return Ok(None);
+            }
+
+            let (js_name, wasm_name) = main_bundle_names(context).await?;
+            let assets_path = &context.config.build.assets_dir;
+            let js_url = format!("/{assets_path}/{js_name}");
+            let wasm_url = format!("/{assets_path}/{wasm_name}");
+            let hot_patch = hot_patch::is_enabled(context);
+
+            let wasm_preload = HtmlTagDescriptor {
+                tag: "link",
+                attrs: HashMap::from([
+                    ("rel", "preload".to_string()),
+                    ("as", "fetch".to_string()),
+                    ("type", "application/wasm".to_string()),
+                    ("href", wasm_url.clone()),
+                ]),
+                children: None,
+                inject_to: HtmlTagInjectTo::Head,
+            };
+
+            if target == WasmBindgenTarget::NoModules {
+                // The classic script defines the `wasm_bindgen` global.
+                let init_script = if hot_patch {
+                    format!(
+                        "wasm_bindgen({{ module_or_path: '{wasm_url}' }}).then((wasm) => {{ window.__thaw_cli_wasm = wasm }})"
+                    )
+                } else {
+                    format!("wasm_bindgen({{ module_or_path: '{wasm_url}' }})")
+                };
+                return Ok(Some(IndexHtmlTransformResult {
+                    tags: vec![
+                        HtmlTagDescriptor {
+                            tag: "link",
+                            attrs: HashMap::from([
+                                ("rel", "preload".to_string()),
+                                ("as", "script".to_string()),
+                                ("href", js_url.clone()),
+                            ]),
+                            children: None,
+                            inject_to: HtmlTagInjectTo::Head,
+                        },
+                        wasm_preload,
+                        HtmlTagDescriptor {
+                            tag: "script",
+                            attrs: HashMap::from([("src", js_url)]),
+                            children: None,
+                            inject_to: HtmlTagInjectTo::Body,
+                        },
+                        HtmlTagDescriptor {
+                            tag: "script",
+                            attrs: HashMap::new(),
+                            children: Some(init_script),
+                            inject_to: HtmlTagInjectTo::Body,
+                        },
+                    ],
+                }));
+            }
+
+            let init_script = if hot_patch {
+                // The client script patches the exports of the running module.
+                format!(
+                    "import init from '{js_url}';window.__thaw_cli_wasm = await init({{ module_or_path: '{wasm_url}' }})"
+                )
+            } else {
+                format!(
+                    "import init from '{js_url}';await init({{ module_or_path: '{wasm_url}' }})"
+                )
+            };
+
+            Ok(Some(IndexHtmlTransformResult {
+                tags: vec![
+                    HtmlTagDescriptor {
+                        tag: "link",
+                        attrs: HashMap::from([
+                            ("rel", "modulepreload".to_string()),
+                            ("href", js_url),
+                        ]),
+                        children: None,
+                        inject_to: HtmlTagInjectTo::Head,
+                    },
+                    wasm_preload,
+                    HtmlTagDescriptor {
+                        tag: "script",
+                        attrs: HashMap::from([("type", "module".to_string())]),
+                        children: Some(init_script),
+                        inject_to: HtmlTagInjectTo::Body,
+                    },
+                ],
+            }))
+        })
+    }
+}
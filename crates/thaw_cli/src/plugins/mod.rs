@@ -1,5 +1,157 @@
+mod client;
+mod command;
 pub mod html;
+mod main_wasm;
 
-// use std::fmt::Debug;
+pub use client::client_script_tag;
 
-// pub trait Plugin: Debug + Send + Sync + 'static {}
+use crate::{build::assets::BundledAsset, config::Config, context::Context};
+use axum::Router;
+use futures_util::future::BoxFuture;
+use html::{IndexHtmlTransformResult, apply_html_transform};
+use std::{fmt::Debug, path::Path};
+
+/// Hooks into the Thaw CLI build and dev server pipeline.
+///
+/// All hooks are optional, plugins are invoked in the order they were registered.
+/// Plugins are built into the CLI, projects add their own steps as `[[plugins]]`
+/// commands in Thaw.toml.
+pub(crate) trait Plugin: Debug + Send + Sync + 'static {
+    /// The name of the plugin, used in error messages.
+    fn name(&self) -> &str;
+
+    /// Called after the config and the context have been resolved.
+    fn config_resolved(&self, _context: &Context) -> color_eyre::Result<()> {
+        Ok(())
+    }
+
+    /// Called at the start of every `thaw build` and the initial `thaw serve` build.
+    fn build_start<'a>(&'a self, _context: &'a Context) -> BoxFuture<'a, color_eyre::Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    /// Returns tags to inject into `index.html`.
+    fn transform_index_html<'a>(
+        &'a self,
+        _context: &'a Context,
+        _html: &'a str,
+    ) -> BoxFuture<'a, color_eyre::Result<Option<IndexHtmlTransformResult>>> {
+        Box::pin(async { Ok(None) })
+    }
+
+    /// Called after wasm-bindgen and wasm-opt wrote the JS/WASM files to `out_dir`.
+    fn after_wasm_bindgen<'a>(
+        &'a self,
+        _context: &'a Context,
+        _out_dir: &'a Path,
+    ) -> BoxFuture<'a, color_eyre::Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    /// Called after all build artifacts have been written to the out_dir.
+    fn write_bundle<'a>(
+        &'a self,
+        _context: &'a Context,
+        _assets: &'a [BundledAsset],
+    ) -> BoxFuture<'a, color_eyre::Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    /// Configures the dev server, e.g. to add custom routes or middlewares.
+    fn configure_server(&self, _context: &Context, router: Router) -> Router {
+        router
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct PluginContainer(Vec<Box<dyn Plugin>>);
+
+impl PluginContainer {
+    pub fn new(config: &Config) -> Self {
+        let mut plugins: Vec<Box<dyn Plugin>> = vec![
+            Box::new(main_wasm::MainWasmPlugin),
+            Box::new(client::ClientPlugin),
+        ];
+        for plugin in &config.plugins {
+            plugins.push(Box::new(command::CommandPlugin::new(plugin.clone())));
+        }
+        Self(plugins)
+    }
+
+    pub fn config_resolved(&self, context: &Context) -> color_eyre::Result<()> {
+        for plugin in &self.0 {
+            plugin.config_resolved(context).map_err(|err| {
+                err.wrap_err(format!("[plugin {}] config_resolved", plugin.name()))
+            })?;
+        }
+        Ok(())
+    }
+
+    pub async fn build_start(&self, context: &Context) -> color_eyre::Result<()> {
+        for plugin in &self.0 {
+            plugin
+                .build_start(context)
+                .await
+                .map_err(|err| err.wrap_err(format!("[plugin {}] build_start", plugin.name())))?;
+        }
+        Ok(())
+    }
+
+    pub async fn transform_index_html(
+        &self,
+        context: &Context,
+        html: String,
+    ) -> color_eyre::Result<String> {
+        let mut tags = vec![];
+        for plugin in &self.0 {
+            let result = plugin
+                .transform_index_html(context, &html)
+                .await
+                .map_err(|err| {
+                    err.wrap_err(format!("[plugin {}] transform_index_html", plugin.name()))
+                })?;
+            if let Some(result) = result {
+                tags.extend(result.tags);
+            }
+        }
+
+        Ok(apply_html_transform(html, tags))
+    }
+
+    pub async fn after_wasm_bindgen(
+        &self,
+        context: &Context,
+        out_dir: &Path,
+    ) -> color_eyre::Result<()> {
+        for plugin in &self.0 {
+            plugin
+                .after_wasm_bindgen(context, out_dir)
+                .await
+                .map_err(|err| {
+                    err.wrap_err(format!("[plugin {}] after_wasm_bindgen", plugin.name()))
+                })?;
+        }
+        Ok(())
+    }
+
+    pub async fn write_bundle(
+        &self,
+        context: &Context,
+        assets: &[BundledAsset],
+    ) -> color_eyre::Result<()> {
+        for plugin in &self.0 {
+            plugin
+                .write_bundle(context, assets)
+                .await
+                .map_err(|err| err.wrap_err(format!("[plugin {}] write_bundle", plugin.name())))?;
+        }
+        Ok(())
+    }
+
+    pub fn configure_server(&self, context: &Context, mut router: Router) -> Router {
+        for plugin in &self.0 {
+            router = plugin.configure_server(context, router);
+        }
+        router
+    }
+}
@@ -1,28 +1,42 @@
-use super::Event;
+use super::{
+    BuildEvents, Cancelled, Event,
+    shortcuts::{self, Shortcut},
+    ws::WsMessage,
+};
 use crate::{
-    build::{assets, clear_out_dir, collect_assets, csr, run_cargo_build, wasm_bindgen},
+    build::{
+        assets, clear_out_dir, collect_assets, csr,
+        hot_patch::{self, HotPatch, HotPatcher},
+        run_cargo_build, wasm_bindgen,
+    },
     context::Context,
     logger,
     utils::DotEyre,
 };
 use globset::{Glob, GlobSetBuilder};
 use notify_debouncer_full::{
-    DebounceEventResult, Debouncer, FileIdMap, new_debouncer,
+    DebounceEventResult, Debouncer, RecommendedCache, new_debouncer,
     notify::{EventKind, RecommendedWatcher, RecursiveMode},
 };
-use std::{path::PathBuf, sync::Arc, time::Duration};
+use std::{collections::VecDeque, path::PathBuf, sync::Arc, time::Duration};
 use tokio::{
     fs,
+    net::TcpListener,
     sync::{broadcast, mpsc},
     task,
 };
+use tokio_util::sync::CancellationToken;
 
 pub struct DevServer {
     context: Arc<Context>,
-    watcher: Debouncer<RecommendedWatcher, FileIdMap>,
+    watcher: Debouncer<RecommendedWatcher, RecommendedCache>,
     assets: Vec<assets::BundledAsset>,
-    event_rx: mpsc::Receiver<Event>,
-    page_tx: Option<broadcast::Sender<()>>,
+    event_tx: mpsc::Sender<Event>,
+    event_rx: Option<mpsc::Receiver<Event>>,
+    page_tx: Option<broadcast::Sender<WsMessage>>,
+    hot_patcher: Option<HotPatcher>,
+    /// Cancelled by Ctrl+C.
+    interrupt: CancellationToken,
 }
 
 impl DevServer {
@@ -35,6 +49,9 @@ impl DevServer {
         }
         let glob_set = builder.build()?;
 
+        let interrupt = CancellationToken::new();
+        shortcuts::listen(event_tx.clone(), interrupt.clone());
+        let watch_tx = event_tx.clone();
         let watcher = new_debouncer(
             Duration::from_millis(500),
             None,
@@ -47,7 +64,7 @@ impl DevServer {
                         .filter(|path| !glob_set.is_match(path))
                         .collect::<Vec<_>>();
                     if !paths.is_empty() {
-                        event_tx.blocking_send(Event::Watch(paths)).unwrap();
+                        watch_tx.blocking_send(Event::Watch(paths)).unwrap();
                     }
                 }
                 Err(e) => println!("watch error: {e:?}"),
@@ -58,15 +75,23 @@ impl DevServer {
             context,
             watcher,
             assets: Vec::new(),
-            event_rx,
+            event_tx,
+            event_rx: Some(event_rx),
             page_tx: None,
+            hot_patcher: None,
+            interrupt,
         })
     }
 
-    pub async fn run(mut self, assets: Vec<assets::BundledAsset>) -> color_eyre::Result<Self> {
-        let src_dir = self.context.current_dir.join("src");
-        self.watcher.watch(src_dir, RecursiveMode::Recursive)?;
-        let index_html = self.context.current_dir.join("index.html");
+    pub async fn run(
+        mut self,
+        assets: Vec<assets::BundledAsset>,
+        listener: TcpListener,
+    ) -> color_eyre::Result<Self> {
+        for src_dir in self.context.src_dirs() {
+            self.watcher.watch(&src_dir, RecursiveMode::Recursive)?;
+        }
+        let index_html = self.context.package_dir.join("index.html");
         self.watcher.watch(index_html, RecursiveMode::Recursive)?;
         self.watch_assets(assets)?;
         for watch in &self.context.config.server.watch.paths {
@@ -78,37 +103,145 @@ impl DevServer {
         task::spawn({
             let context = self.context.clone();
             let page_tx = page_tx.clone();
-            async move { super::csr_app::run(context, page_tx).await }
+            let event_tx = self.event_tx.clone();
+            async move { super::csr_app::run(context, page_tx, event_tx, listener).await }
         });
         self.page_tx = Some(page_tx);
+        self.reload_hot_patcher().await?;
         Ok(self)
     }
 
     pub async fn wait_event(mut self) -> color_eyre::Result<()> {
-        while let Some(event) = self.event_rx.recv().await {
+        let mut event_rx = self.event_rx.take().unwrap();
+        let mut pending_events = VecDeque::new();
+        loop {
+            let event = match pending_events.pop_front() {
+                Some(event) => event,
+                None => tokio::select! {
+                    event = event_rx.recv() => match event {
+                        Some(event) => event,
+                        None => break,
+                    },
+                    _ = self.interrupt.cancelled() => break,
+                },
+            };
             match event {
                 Event::Watch(paths) => {
                     if paths.is_empty() {
                         continue;
                     }
 
-                    let build_result = self.rebuild(&paths).await;
-
+                    let Some((paths, build_result)) = self
+                        .rebuild_latest(Some(paths), &mut event_rx, &mut pending_events)
+                        .await
+                    else {
+                        break;
+                    };
+                    self.send_build_result(paths, build_result).await?;
+                }
+                Event::Shortcut(Shortcut::Rebuild) => {
+                    let Some((paths, build_result)) = self
+                        .rebuild_latest(None, &mut event_rx, &mut pending_events)
+                        .await
+                    else {
+                        break;
+                    };
+                    self.send_build_result(paths, build_result).await?;
+                }
+                Event::HotPatchFailed(error) => {
                     self.context
                         .logger
-                        .send(logger::Message::PageReload(paths, build_result))
+                        .send(logger::Message::Build(format!(
+                            "Failed to apply the hot patch, rebuilding: {error}"
+                        )))
                         .await?;
+                    let Some((paths, build_result)) = self
+                        .rebuild_latest(None, &mut event_rx, &mut pending_events)
+                        .await
+                    else {
+                        break;
+                    };
+                    self.send_build_result(paths, build_result).await?;
                 }
+                Event::Shortcut(Shortcut::Quit) => break,
+                Event::Shortcut(shortcut) => shortcuts::run(&self.context, shortcut, false)?,
             }
         }
         Ok(())
     }
 
-    async fn rebuild(&mut self, paths: &Vec<PathBuf>) -> color_eyre::Result<()> {
-        if paths.len() == 1 && paths[0] == self.context.current_dir.join("index.html") {
+    /// Rebuilds for the changed paths, or everything for `None`. When more files change
+    /// during the cargo build, it is cancelled and restarted once with all of the changed
+    /// paths. Other events are queued until the rebuild finishes. Returns `None` on quit.
+    async fn rebuild_latest(
+        &mut self,
+        mut paths: Option<Vec<PathBuf>>,
+        event_rx: &mut mpsc::Receiver<Event>,
+        pending_events: &mut VecDeque<Event>,
+    ) -> Option<(Vec<PathBuf>, color_eyre::Result<WsMessage>)> {
+        let mut events = BuildEvents {
+            event_rx,
+            pending_events,
+            interrupt: self.interrupt.clone(),
+        };
+        loop {
+            let build_result = match &paths {
+                Some(paths) => self.rebuild(paths, &mut events).await,
+                None => self
+                    .rebuild_all(&mut events)
+                    .await
+                    .map(|_| WsMessage::RefreshPage),
+            };
+            let cancelled = build_result
+                .as_ref()
+                .err()
+                .and_then(|err| err.downcast_ref::<Cancelled>());
+            match cancelled {
+                Some(Cancelled::Watch(new_paths)) => {
+                    if let Some(paths) = &mut paths {
+                        for path in new_paths {
+                            if !paths.contains(path) {
+                                paths.push(path.clone());
+                            }
+                        }
+                    }
+                }
+                Some(Cancelled::Quit) => return None,
+                None => return Some((paths.unwrap_or_default(), build_result)),
+            }
+        }
+    }
+
+    async fn send_build_result(
+        &self,
+        paths: Vec<PathBuf>,
+        build_result: color_eyre::Result<WsMessage>,
+    ) -> color_eyre::Result<()> {
+        let message = match &build_result {
+            Ok(message) => message.clone(),
+            Err(err) => WsMessage::build_error(err),
+        };
+        // When no page is open, this send will report an error.
+        let _ = self.page_tx.as_ref().unwrap().send(message);
+
+        let message = match build_result {
+            Ok(WsMessage::AssetUpdate { .. }) => logger::Message::HotUpdate(paths),
+            Ok(WsMessage::HotPatch(_)) => logger::Message::HotPatch(paths),
+            build_result => logger::Message::PageReload(paths, build_result.map(|_| ())),
+        };
+        self.context.logger.send(message).await?;
+        Ok(())
+    }
+
+    async fn rebuild(
+        &mut self,
+        paths: &Vec<PathBuf>,
+        events: &mut BuildEvents<'_>,
+    ) -> color_eyre::Result<WsMessage> {
+        if paths.len() == 1 && paths[0] == self.context.package_dir.join("index.html") {
             csr::build_index_html(&self.context).await?;
         } else if let Some(asset_subset) = assets::asset_subset(&self.assets, paths) {
-            for asset in asset_subset {
+            for asset in &asset_subset {
                 fs::remove_file(&asset.output_path).await?;
                 dioxus_cli_opt::process_file_to(
                     &asset.options,
@@ -117,21 +250,82 @@ impl DevServer {
                 )
                 .dot_eyre()?;
             }
+            if let Some(paths) = assets::css_update_paths(&asset_subset, &self.context.out_dir) {
+                return Ok(WsMessage::AssetUpdate { paths });
+            }
+        } else if let Some(hot_patch) = self.hot_patch(paths).await? {
+            return Ok(WsMessage::HotPatch(hot_patch));
         } else {
-            let wasm_path =
-                run_cargo_build(&self.context, csr::cargo_build_args(&self.context)).await?;
-            clear_out_dir(&self.context).await?;
-            fs::create_dir_all(&self.context.assets_dir).await?;
-            let assets = collect_assets(&self.context, wasm_path, &self.context.assets_dir).await?;
-            wasm_bindgen(&self.context, None, &self.context.assets_dir).await?;
-            self.watch_assets(assets)?;
+            self.rebuild_all(events).await?;
         }
 
-        // When no page is open, this send will report an error.
-        let _ = self.page_tx.as_ref().unwrap().send(());
+        Ok(WsMessage::RefreshPage)
+    }
+
+    /// Returns `None` when a full rebuild is needed.
+    async fn hot_patch(&mut self, paths: &[PathBuf]) -> color_eyre::Result<Option<HotPatch>> {
+        let Some(hot_patcher) = &mut self.hot_patcher else {
+            return Ok(None);
+        };
+        if !paths
+            .iter()
+            .all(|path| path.extension().is_some_and(|ext| ext == "rs"))
+        {
+            return Ok(None);
+        }
+
+        match hot_patcher.patch(&self.context).await {
+            Ok(hot_patch) => Ok(Some(hot_patch)),
+            Err(err) => {
+                self.context
+                    .logger
+                    .send(logger::Message::Build(format!(
+                        "Falling back to a full rebuild: {err}"
+                    )))
+                    .await?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Hot patches are made against the latest full build.
+    async fn reload_hot_patcher(&mut self) -> color_eyre::Result<()> {
+        self.hot_patcher = None;
+        if !hot_patch::is_enabled(&self.context) {
+            return Ok(());
+        }
+        self.hot_patcher = match HotPatcher::new(&self.context).await {
+            Ok(hot_patcher) => Some(hot_patcher),
+            Err(err) => {
+                self.context
+                    .logger
+                    .send(logger::Message::Build(format!(
+                        "Hot patching is unavailable: {err}"
+                    )))
+                    .await?;
+                None
+            }
+        };
         Ok(())
     }
 
+    async fn rebuild_all(&mut self, events: &mut BuildEvents<'_>) -> color_eyre::Result<()> {
+        let wasm_path = events
+            .cancellable(run_cargo_build(
+                &self.context,
+                csr::cargo_build_args(&self.context),
+                &self.context.config.build.client,
+                None,
+            ))
+            .await?;
+        clear_out_dir(&self.context).await?;
+        fs::create_dir_all(&self.context.assets_dir).await?;
+        let assets = collect_assets(&self.context, wasm_path, &self.context.assets_dir).await?;
+        wasm_bindgen(&self.context, None, &self.context.assets_dir).await?;
+        self.watch_assets(assets)?;
+        events.cancellable(self.reload_hot_patcher()).await
+    }
+
     fn watch_assets(&mut self, assets: Vec<assets::BundledAsset>) -> color_eyre::Result<()> {
         for asset in &self.assets {
             self.watcher.unwatch(&asset.absolute_source_path)?;
@@ -1,7 +1,7 @@
 use super::{
-    middlewares,
+    Event, middlewares,
     open_browser::open_browser,
-    ws::{ThawCliWs, thaw_cli_ws},
+    ws::{ThawCliWs, WsMessage, thaw_cli_ws},
 };
 use crate::{constants::THAW_CLI_WS_PATH, context::Context};
 use axum::{
@@ -9,14 +9,22 @@ use axum::{
     routing::{get, get_service},
 };
 use std::sync::Arc;
-use tokio::{net::TcpListener, sync::broadcast};
+use tokio::{
+    net::TcpListener,
+    sync::{broadcast, mpsc},
+};
 use tower_http::{
     compression::CompressionLayer,
     services::{ServeDir, ServeFile},
 };
 
-pub async fn run(context: Arc<Context>, tx: broadcast::Sender<()>) -> color_eyre::Result<()> {
-    let state = ThawCliWs::new(tx);
+pub async fn run(
+    context: Arc<Context>,
+    tx: broadcast::Sender<WsMessage>,
+    event_tx: mpsc::Sender<Event>,
+    listener: TcpListener,
+) -> color_eyre::Result<()> {
+    let state = ThawCliWs::new(tx, Some(event_tx));
     let out_dir = &context.out_dir;
 
     let public_dir = context.current_dir.join(context.config.public_dir.clone());
@@ -37,29 +45,18 @@ pub async fn run(context: Arc<Context>, tx: broadcast::Sender<()>) -> color_eyre
     let app = Router::new()
         .route(THAW_CLI_WS_PATH, get(thaw_cli_ws))
         .fallback_service(get_service(serve_dir))
-        .with_state(state)
+        .with_state(state);
+    let app = context
+        .plugins
+        .configure_server(&context, app)
         .layer(middlewares::ProxyLayer::new(&context))
         .layer(CompressionLayer::new());
 
-    let addr = format!(
-        "{}:{}",
-        context.config.server.host, context.config.server.port
-    );
-
-    let listener = match TcpListener::bind(addr).await {
-        Ok(listener) => listener,
-        Err(err) => return color_eyre::Result::Err(err.into()),
-    };
-
     if context.open {
-        let url = format!(
-            "http://{}:{}",
-            context.config.server.host, context.config.server.port,
-        );
-        open_browser(&context, url)?;
+        open_browser(&context, super::local_url(&context))?;
     }
 
-    axum::serve(listener, app).await?;
+    super::serve(&context, listener, app).await?;
 
     Ok(())
 }
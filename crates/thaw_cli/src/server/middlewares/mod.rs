@@ -1,4 +1,3 @@
-pub mod index_html;
 mod proxy;
 
 pub use proxy::*;
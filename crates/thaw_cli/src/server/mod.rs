@@ -2,17 +2,82 @@ pub mod csr;
 mod csr_app;
 pub mod middlewares;
 mod open_browser;
+pub mod preview;
+mod shortcuts;
 pub mod ssr;
 mod ssr_app;
+mod tls;
 mod ws;
 
 use crate::{context::Context, logger};
-use color_eyre::owo_colors::OwoColorize;
-use std::{path::PathBuf, sync::Arc};
+use axum::Router;
+use color_eyre::{eyre::eyre, owo_colors::OwoColorize};
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::conn::auto,
+    service::TowerToHyperService,
+};
+use std::{
+    collections::VecDeque, fmt, io::ErrorKind, net::IpAddr, path::PathBuf, sync::Arc,
+    time::Duration,
+};
+use tokio::{net::TcpListener, sync::mpsc, task, time};
+use tokio_util::sync::CancellationToken;
 
 #[derive(Debug)]
 enum Event {
     Watch(Vec<PathBuf>),
+    Shortcut(shortcuts::Shortcut),
+    /// The client could not apply a hot patch.
+    HotPatchFailed(String),
+}
+
+/// Receives the events that arrive while a rebuild runs.
+struct BuildEvents<'a> {
+    event_rx: &'a mut mpsc::Receiver<Event>,
+    /// Events handled after the rebuild.
+    pending_events: &'a mut VecDeque<Event>,
+    interrupt: CancellationToken,
+}
+
+/// The error of a rebuild cancelled by [`BuildEvents::cancellable`].
+#[derive(Debug)]
+enum Cancelled {
+    /// More files changed.
+    Watch(Vec<PathBuf>),
+    /// Ctrl+C or the quit shortcut.
+    Quit,
+}
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("The build was cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+impl BuildEvents<'_> {
+    /// Runs a step of a rebuild that can be dropped when more files change or on quit:
+    /// cargo or loading the hot patch baseline. The out_dir and the SSR server are never
+    /// left half-updated.
+    async fn cancellable<T>(
+        &mut self,
+        build: impl Future<Output = color_eyre::Result<T>>,
+    ) -> color_eyre::Result<T> {
+        tokio::pin!(build);
+        loop {
+            tokio::select! {
+                build_result = &mut build => return build_result,
+                Some(event) = self.event_rx.recv() => match event {
+                    Event::Watch(paths) => return Err(Cancelled::Watch(paths).into()),
+                    Event::Shortcut(shortcuts::Shortcut::Quit) => return Err(Cancelled::Quit.into()),
+                    event => self.pending_events.push_back(event),
+                },
+                _ = self.interrupt.cancelled() => return Err(Cancelled::Quit.into()),
+            }
+        }
+    }
 }
 
 pub async fn init_build_finished(context: &Arc<Context>) -> color_eyre::Result<()> {
@@ -28,15 +93,157 @@ pub async fn init_build_finished(context: &Arc<Context>) -> color_eyre::Result<(
         format!("Thaw CLI v{}", context.create_version).green(),
         time.bold()
     );
+    print_urls(context);
+    Ok(())
+}
+
+pub fn print_urls(context: &Context) {
     println!(
         "  {}  {}: {}",
         "➜".green(),
         "Local".bold(),
-        format!(
-            "http://{}:{}",
-            context.config.server.host, context.config.server.port,
-        )
-        .cyan()
+        local_url(context).cyan()
     );
-    Ok(())
+
+    if !is_wildcard_host(&context.config.server.host) {
+        println!(
+            "  {}  {}: {}",
+            "➜".green().dimmed(),
+            "Network".bold().dimmed(),
+            "use --host to expose".dimmed()
+        );
+        return;
+    }
+    for ip in network_ips() {
+        if !ip.is_ipv4() {
+            continue;
+        }
+        println!(
+            "  {}  {}: {}",
+            "➜".green(),
+            "Network".bold(),
+            url(context, &ip.to_string()).cyan()
+        );
+    }
+}
+
+/// The IPs of the non-loopback network interfaces, reachable from other devices.
+fn network_ips() -> Vec<IpAddr> {
+    let Ok(interfaces) = if_addrs::get_if_addrs() else {
+        return vec![];
+    };
+    interfaces
+        .into_iter()
+        .filter(|interface| !interface.is_loopback())
+        .map(|interface| interface.ip())
+        .collect()
+}
+
+/// The local URL of the dev server.
+pub fn local_url(context: &Context) -> String {
+    let host = &context.config.server.host;
+    if is_wildcard_host(host) {
+        url(context, "localhost")
+    } else {
+        url(context, host)
+    }
+}
+
+fn url(context: &Context, host: &str) -> String {
+    let scheme = if context.config.server.https.is_enabled() {
+        "https"
+    } else {
+        "http"
+    };
+    format!("{scheme}://{}", host_port(host, context.config.server.port))
+}
+
+/// `host:port`, with IPv6 addresses in brackets.
+pub fn host_port(host: &str, port: u32) -> String {
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V6(_)) => format!("[{host}]:{port}"),
+        _ => format!("{host}:{port}"),
+    }
+}
+
+fn is_wildcard_host(host: &str) -> bool {
+    host.parse::<IpAddr>().is_ok_and(|ip| ip.is_unspecified())
+}
+
+/// Binds the dev server port. Unless `server.strict_port` is enabled,
+/// the next ports are tried when the port is already in use.
+pub async fn bind_listener(context: &mut Context) -> color_eyre::Result<TcpListener> {
+    let server = &mut context.config.server;
+    let mut port = server.port;
+    loop {
+        match TcpListener::bind(host_port(&server.host, port)).await {
+            Ok(listener) => {
+                server.port = listener.local_addr()?.port().into();
+                return Ok(listener);
+            }
+            Err(err) if err.kind() == ErrorKind::AddrInUse => {
+                if server.strict_port || port >= u16::MAX.into() {
+                    return Err(eyre!("Port {port} is already in use"));
+                }
+                println!(
+                    "{}",
+                    format!("Port {port} is in use, trying another one...").yellow()
+                );
+                port += 1;
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+/// Serves the dev server app, over TLS when `server.https` is enabled.
+async fn serve(context: &Context, listener: TcpListener, app: Router) -> color_eyre::Result<()> {
+    let Some(tls_acceptor) = tls::tls_acceptor(context).await? else {
+        axum::serve(listener, app).await?;
+        return Ok(());
+    };
+
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            // E.g. too many open files or a connection reset before it was accepted.
+            Err(err) => {
+                context
+                    .logger
+                    .send(logger::Message::CargoPackaging(
+                        format!("error: failed to accept a connection: {err}").into(),
+                    ))
+                    .await?;
+                time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+        let tls_acceptor = tls_acceptor.clone();
+        let service = TowerToHyperService::new(app.clone());
+        task::spawn(async move {
+            // Handshake errors, e.g. an untrusted certificate, only affect this connection.
+            let Ok(stream) = tls_acceptor.accept(stream).await else {
+                return;
+            };
+            let _ = auto::Builder::new(TokioExecutor::new())
+                .serve_connection_with_upgrades(TokioIo::new(stream), service)
+                .await;
+        });
+    }
+}
+
+#[test]
+fn test_is_wildcard_host() {
+    assert!(is_wildcard_host("0.0.0.0"));
+    assert!(is_wildcard_host("::"));
+    assert!(!is_wildcard_host("localhost"));
+    assert!(!is_wildcard_host("127.0.0.1"));
+}
+
+#[test]
+fn test_host_port() {
+    assert_eq!(host_port("localhost", 6321), "localhost:6321");
+    assert_eq!(host_port("0.0.0.0", 6321), "0.0.0.0:6321");
+    assert_eq!(host_port("::", 6321), "[::]:6321");
+    assert_eq!(host_port("::1", 6321), "[::1]:6321");
 }
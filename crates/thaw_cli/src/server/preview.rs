@@ -0,0 +1,111 @@
+use super::{host_port, middlewares, open_browser::open_browser};
+use crate::{build::cargo_build_exe_name, context::Context};
+use axum::{Router, routing::get_service};
+use color_eyre::{eyre::eyre, owo_colors::OwoColorize};
+use std::collections::HashMap;
+use tokio::{fs, net::TcpListener, process::Command};
+use tower_http::{
+    compression::CompressionLayer,
+    services::{ServeDir, ServeFile},
+};
+
+/// Serves the `thaw build csr` output as is.
+pub async fn run_csr(context: &Context) -> color_eyre::Result<()> {
+    let out_dir = &context.out_dir;
+    let index_html = out_dir.join("index.html");
+    if !fs::try_exists(&index_html).await? {
+        return Err(eyre!(
+            "No build output was found in {out_dir:?}. Run `thaw build csr` first."
+        ));
+    }
+
+    let serve_dir = ServeDir::new(out_dir)
+        .precompressed_br()
+        .precompressed_zstd()
+        .precompressed_gzip()
+        .precompressed_deflate()
+        .fallback(
+            ServeFile::new(index_html)
+                .precompressed_br()
+                .precompressed_zstd()
+                .precompressed_gzip()
+                .precompressed_deflate(),
+        );
+
+    let app = Router::new()
+        .fallback_service(get_service(serve_dir))
+        .layer(middlewares::ProxyLayer::new(context))
+        .layer(CompressionLayer::new());
+
+    let addr = host_port(&context.config.preview.host, context.config.preview.port);
+    let listener = TcpListener::bind(&addr).await?;
+
+    let url = format!("http://{addr}");
+    print_url(context, &url);
+    if context.open {
+        open_browser(context, url)?;
+    }
+
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
+
+/// Launches the `thaw build ssr` executable with its generated `.env` file.
+pub async fn run_ssr(context: &Context) -> color_eyre::Result<()> {
+    let client_out_dir = context.out_dir.join("client");
+    let server_out_dir = context.out_dir.join("server");
+    let exe_path = server_out_dir.join(cargo_build_exe_name(context)?);
+    if !fs::try_exists(&exe_path).await? {
+        return Err(eyre!(
+            "No build output was found in {:?}. Run `thaw build ssr` first.",
+            context.out_dir
+        ));
+    }
+
+    let mut envs = HashMap::new();
+    let env_path = server_out_dir.join(".env");
+    if fs::try_exists(&env_path).await? {
+        for item in dotenvy::from_path_iter(env_path)? {
+            let (key, value) = item?;
+            envs.insert(key, value);
+        }
+    }
+    if !envs.contains_key("LEPTOS_SITE_ROOT") {
+        envs.insert(
+            "LEPTOS_SITE_ROOT".to_string(),
+            client_out_dir.display().to_string(),
+        );
+    }
+
+    let site_addr = envs
+        .get("LEPTOS_SITE_ADDR")
+        .cloned()
+        .unwrap_or_else(|| "127.0.0.1:3000".to_string());
+
+    let mut cmd = Command::new(exe_path);
+    cmd.current_dir(&server_out_dir);
+    cmd.envs(envs);
+    let mut child = cmd.kill_on_drop(true).spawn()?;
+
+    let url = format!("http://{site_addr}");
+    print_url(context, &url);
+    if context.open {
+        open_browser(context, url)?;
+    }
+
+    let status = child.wait().await?;
+    if !status.success() {
+        return Err(eyre!("The SSR server exited with {status}"));
+    }
+
+    Ok(())
+}
+
+fn print_url(context: &Context, url: &str) {
+    println!(
+        "\n  {}  preview\n",
+        format!("Thaw CLI v{}", context.create_version).green(),
+    );
+    println!("  {}  {}: {}", "➜".green(), "Local".bold(), url.cyan());
+}
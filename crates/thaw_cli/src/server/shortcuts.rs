@@ -0,0 +1,148 @@
+use super::{Event, local_url, open_browser::open_browser, print_urls};
+use crate::context::Context;
+use color_eyre::owo_colors::OwoColorize;
+use crossterm::{cursor, execute, terminal};
+use std::{
+    io::{self, BufRead, IsTerminal},
+    thread,
+};
+use tokio::{signal, sync::mpsc, task};
+use tokio_util::sync::CancellationToken;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shortcut {
+    Rebuild,
+    Open,
+    Clear,
+    Urls,
+    RestartServer,
+    Quit,
+    Help,
+}
+
+impl Shortcut {
+    const ALL: [Self; 7] = [
+        Self::Rebuild,
+        Self::Open,
+        Self::Clear,
+        Self::Urls,
+        Self::RestartServer,
+        Self::Quit,
+        Self::Help,
+    ];
+
+    /// A line with only the key of a shortcut.
+    fn from_input(input: &str) -> Option<Self> {
+        let mut chars = input.trim().chars();
+        let (Some(char), None) = (chars.next(), chars.next()) else {
+            return None;
+        };
+        Self::ALL
+            .into_iter()
+            .find(|shortcut| shortcut.key() == char.to_ascii_lowercase())
+    }
+
+    fn key(&self) -> char {
+        match self {
+            Self::Rebuild => 'r',
+            Self::Open => 'o',
+            Self::Clear => 'c',
+            Self::Urls => 'u',
+            Self::RestartServer => 's',
+            Self::Quit => 'q',
+            Self::Help => 'h',
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            Self::Rebuild => "force a full rebuild",
+            Self::Open => "open in browser",
+            Self::Clear => "clear console",
+            Self::Urls => "show server url",
+            Self::RestartServer => "restart the SSR server",
+            Self::Quit => "quit",
+            Self::Help => "show help",
+        }
+    }
+}
+
+/// Reads shortcut keys, each followed by Enter, from the terminal and sends them as events.
+/// Ctrl+C cancels `interrupt` right away, without waiting behind a running build.
+/// No shortcuts are read when stdin is not a terminal.
+pub fn listen(event_tx: mpsc::Sender<Event>, interrupt: CancellationToken) {
+    task::spawn(async move {
+        if signal::ctrl_c().await.is_ok() {
+            interrupt.cancel();
+        }
+    });
+
+    if !io::stdin().is_terminal() {
+        return;
+    }
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if let Some(shortcut) = Shortcut::from_input(&line)
+                && event_tx.blocking_send(Event::Shortcut(shortcut)).is_err()
+            {
+                break;
+            }
+        }
+    });
+
+    println!(
+        "  {}  {}",
+        "➜".green().dimmed(),
+        format!("press {} to show help", "h + enter".bold()).dimmed()
+    );
+}
+
+/// Runs the shortcuts that do not depend on the dev server state.
+pub fn run(context: &Context, shortcut: Shortcut, ssr: bool) -> color_eyre::Result<()> {
+    match shortcut {
+        Shortcut::Open => open_browser(context, local_url(context))?,
+        Shortcut::Clear => {
+            execute!(
+                io::stdout(),
+                terminal::Clear(terminal::ClearType::All),
+                terminal::Clear(terminal::ClearType::Purge),
+                cursor::MoveTo(0, 0)
+            )?;
+        }
+        Shortcut::Urls => {
+            println!();
+            print_urls(context);
+        }
+        Shortcut::Help => print_help(ssr),
+        // Handled by the dev server.
+        Shortcut::Rebuild | Shortcut::RestartServer | Shortcut::Quit => {}
+    }
+    Ok(())
+}
+
+fn print_help(ssr: bool) {
+    println!("\n  {}", "Shortcuts".bold());
+    for shortcut in Shortcut::ALL {
+        if !ssr && shortcut == Shortcut::RestartServer {
+            continue;
+        }
+        println!(
+            "  {} {} {}",
+            "press".dimmed(),
+            format!("{} + enter", shortcut.key()).bold(),
+            format!("to {}", shortcut.description()).dimmed()
+        );
+    }
+}
+
+#[test]
+fn test_shortcut_from_input() {
+    assert_eq!(Shortcut::from_input("r\n"), Some(Shortcut::Rebuild));
+    assert_eq!(Shortcut::from_input(" Q "), Some(Shortcut::Quit));
+    assert_eq!(Shortcut::from_input("x"), None);
+    assert_eq!(Shortcut::from_input("rr"), None);
+    assert_eq!(Shortcut::from_input(""), None);
+}
@@ -1,18 +1,32 @@
-use super::Event;
+use super::{
+    BuildEvents, Cancelled, Event,
+    shortcuts::{self, Shortcut},
+    ws::WsMessage,
+};
 use crate::{
-    build::{assets, cargo_build_exe_name, collect_assets, hydrate, run_cargo_build, wasm_bindgen},
+    build::{
+        assets, cargo_build_exe_name, collect_assets, ssr,
+        wasm::{build_wasm_path, is_hash_filenames},
+        wasm_bindgen,
+    },
     context::Context,
     logger,
     utils::{DotEyre, fs::clear_dir},
 };
 use globset::{Glob, GlobSetBuilder};
 use notify_debouncer_full::{
-    DebounceEventResult, Debouncer, FileIdMap, new_debouncer,
+    DebounceEventResult, Debouncer, RecommendedCache, new_debouncer,
     notify::{EventKind, RecommendedWatcher, RecursiveMode},
 };
-use std::{path::PathBuf, sync::Arc, time::Duration};
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 use tokio::{
     fs,
+    net::TcpListener,
     process::{Child, Command},
     sync::{broadcast, mpsc},
     task::{self, JoinHandle},
@@ -21,14 +35,16 @@ use tokio_util::sync::CancellationToken;
 
 pub struct DevServer {
     context: Arc<Context>,
-    watcher: Debouncer<RecommendedWatcher, FileIdMap>,
+    watcher: Debouncer<RecommendedWatcher, RecommendedCache>,
     assets: Vec<assets::BundledAsset>,
-    event_rx: mpsc::Receiver<Event>,
+    event_rx: Option<mpsc::Receiver<Event>>,
 
     ssr_exe_join_handle: Option<JoinHandle<color_eyre::Result<()>>>,
     cancellation_token: Option<CancellationToken>,
 
-    page_tx: Option<broadcast::Sender<()>>,
+    page_tx: Option<broadcast::Sender<WsMessage>>,
+    /// Cancelled by Ctrl+C.
+    interrupt: CancellationToken,
 }
 
 impl DevServer {
@@ -41,6 +57,8 @@ impl DevServer {
         }
         let glob_set = builder.build()?;
 
+        let interrupt = CancellationToken::new();
+        shortcuts::listen(event_tx.clone(), interrupt.clone());
         let watcher = new_debouncer(
             Duration::from_millis(500),
             None,
@@ -64,18 +82,24 @@ impl DevServer {
             context,
             watcher,
             assets: Vec::new(),
-            event_rx,
+            event_rx: Some(event_rx),
 
             ssr_exe_join_handle: None,
             cancellation_token: None,
 
             page_tx: None,
+            interrupt,
         })
     }
 
-    pub async fn run(mut self, assets: Vec<assets::BundledAsset>) -> color_eyre::Result<Self> {
-        let src_dir = self.context.current_dir.join("src");
-        self.watcher.watch(src_dir, RecursiveMode::Recursive)?;
+    pub async fn run(
+        mut self,
+        assets: Vec<assets::BundledAsset>,
+        listener: TcpListener,
+    ) -> color_eyre::Result<Self> {
+        for src_dir in self.context.src_dirs() {
+            self.watcher.watch(&src_dir, RecursiveMode::Recursive)?;
+        }
         self.watch_assets(assets)?;
         for watch in &self.context.config.server.watch.paths {
             let path = self.context.current_dir.join(&watch.path);
@@ -88,30 +112,132 @@ impl DevServer {
         task::spawn({
             let context = self.context.clone();
             let page_tx = page_tx.clone();
-            async move { super::ssr_app::run(context, page_tx).await }
+            async move { super::ssr_app::run(context, page_tx, listener).await }
         });
         self.page_tx = Some(page_tx);
         Ok(self)
     }
 
     pub async fn wait_event(mut self) -> color_eyre::Result<()> {
-        while let Some(event) = self.event_rx.recv().await {
+        let mut event_rx = self.event_rx.take().unwrap();
+        let mut pending_events = VecDeque::new();
+        loop {
+            let event = match pending_events.pop_front() {
+                Some(event) => event,
+                None => tokio::select! {
+                    event = event_rx.recv() => match event {
+                        Some(event) => event,
+                        None => break,
+                    },
+                    _ = self.interrupt.cancelled() => break,
+                },
+            };
             match event {
                 Event::Watch(paths) => {
-                    let build_result = self.rebuild(&paths).await;
-                    self.context
-                        .logger
-                        .send(logger::Message::PageReload(paths, build_result))
-                        .await?;
+                    let Some((paths, build_result)) = self
+                        .rebuild_latest(Some(paths), &mut event_rx, &mut pending_events)
+                        .await
+                    else {
+                        break;
+                    };
+                    self.send_build_result(paths, build_result).await?;
+                }
+                Event::Shortcut(Shortcut::Rebuild) => {
+                    let Some((paths, build_result)) = self
+                        .rebuild_latest(None, &mut event_rx, &mut pending_events)
+                        .await
+                    else {
+                        break;
+                    };
+                    self.send_build_result(paths, build_result).await?;
+                }
+                Event::Shortcut(Shortcut::RestartServer) => {
+                    self.abort_ssr_exe().await?;
+                    self.run_ssr_exe();
                 }
+                // Hot patching is only supported by `thaw serve csr`.
+                Event::HotPatchFailed(_) => {}
+                Event::Shortcut(Shortcut::Quit) => break,
+                Event::Shortcut(shortcut) => shortcuts::run(&self.context, shortcut, true)?,
             }
         }
+        self.abort_ssr_exe().await?;
         Ok(())
     }
 
-    async fn rebuild(&mut self, paths: &Vec<PathBuf>) -> color_eyre::Result<()> {
+    /// Rebuilds for the changed paths, or everything for `None`. When more files change
+    /// during the cargo build, it is cancelled and restarted once with all of the changed
+    /// paths. Other events are queued until the rebuild finishes. Returns `None` on quit.
+    async fn rebuild_latest(
+        &mut self,
+        mut paths: Option<Vec<PathBuf>>,
+        event_rx: &mut mpsc::Receiver<Event>,
+        pending_events: &mut VecDeque<Event>,
+    ) -> Option<(Vec<PathBuf>, color_eyre::Result<Option<WsMessage>>)> {
+        let mut events = BuildEvents {
+            event_rx,
+            pending_events,
+            interrupt: self.interrupt.clone(),
+        };
+        loop {
+            let build_result = match &paths {
+                Some(paths) => self.rebuild(paths, &mut events).await,
+                None => self
+                    .rebuild_all(&mut events)
+                    .await
+                    .map(|_| Some(WsMessage::RefreshPage)),
+            };
+            let cancelled = build_result
+                .as_ref()
+                .err()
+                .and_then(|err| err.downcast_ref::<Cancelled>());
+            match cancelled {
+                Some(Cancelled::Watch(new_paths)) => {
+                    if let Some(paths) = &mut paths {
+                        for path in new_paths {
+                            if !paths.contains(path) {
+                                paths.push(path.clone());
+                            }
+                        }
+                    }
+                }
+                Some(Cancelled::Quit) => return None,
+                None => return Some((paths.unwrap_or_default(), build_result)),
+            }
+        }
+    }
+
+    /// `Ok(None)` means nothing the page uses changed, so it is not reloaded.
+    async fn send_build_result(
+        &self,
+        paths: Vec<PathBuf>,
+        build_result: color_eyre::Result<Option<WsMessage>>,
+    ) -> color_eyre::Result<()> {
+        let message = match &build_result {
+            Ok(message) => message.clone(),
+            Err(err) => Some(WsMessage::build_error(err)),
+        };
+        if let Some(message) = message {
+            // When no page is open, this send will report an error.
+            let _ = self.page_tx.as_ref().unwrap().send(message);
+        }
+
+        let message = match build_result {
+            Ok(Some(WsMessage::AssetUpdate { .. })) => logger::Message::HotUpdate(paths),
+            Ok(None) => logger::Message::Unchanged(paths),
+            build_result => logger::Message::PageReload(paths, build_result.map(|_| ())),
+        };
+        self.context.logger.send(message).await?;
+        Ok(())
+    }
+
+    async fn rebuild(
+        &mut self,
+        paths: &Vec<PathBuf>,
+        events: &mut BuildEvents<'_>,
+    ) -> color_eyre::Result<Option<WsMessage>> {
         if let Some(asset_subset) = assets::asset_subset(&self.assets, paths) {
-            for asset in asset_subset {
+            for asset in &asset_subset {
                 fs::remove_file(&asset.output_path).await?;
                 dioxus_cli_opt::process_file_to(
                     &asset.options,
@@ -120,34 +246,83 @@ impl DevServer {
                 )
                 .dot_eyre()?;
             }
+            if let Some(paths) =
+                assets::css_update_paths(&asset_subset, &self.context.out_dir.join("client"))
+            {
+                return Ok(Some(WsMessage::AssetUpdate { paths }));
+            }
         } else {
-            let client_out_dir = self.context.out_dir.join("client");
-            let server_out_dir = self.context.out_dir.join("server");
-            let assets_dir = client_out_dir.join(&self.context.config.build.assets_dir);
-
-            run_cargo_build(&self.context, hydrate::cargo_build_args()).await?;
-            clear_dir(&assets_dir).await?;
-            wasm_bindgen(&self.context, None, &assets_dir).await?;
+            let (client, server) = ssr::affected_builds(&self.context, paths).await;
+            if !self.rebuild_builds(client, server, events).await? {
+                return Ok(None);
+            }
+        }
+        Ok(Some(WsMessage::RefreshPage))
+    }
 
-            let exe_path = run_cargo_build(&self.context, vec!["--features=ssr"])
-                .await?
-                .unwrap();
-            let assets = collect_assets(&self.context, Some(exe_path.clone()), &assets_dir).await?;
-            self.watch_assets(assets)?;
+    async fn rebuild_all(&mut self, events: &mut BuildEvents<'_>) -> color_eyre::Result<()> {
+        self.rebuild_builds(true, true, events).await?;
+        Ok(())
+    }
 
-            self.abort_ssr_exe().await?;
+    /// Runs the hydrate and/or the server build. The bundle is only regenerated and the
+    /// server only restarted when cargo rebuilt them. Returns whether either was.
+    async fn rebuild_builds(
+        &mut self,
+        client: bool,
+        server: bool,
+        events: &mut BuildEvents<'_>,
+    ) -> color_eyre::Result<bool> {
+        let client_out_dir = self.context.out_dir.join("client");
+        let server_out_dir = self.context.out_dir.join("server");
+        let assets_dir = client_out_dir.join(&self.context.config.build.assets_dir);
+        let server_exe_path = server_out_dir.join(cargo_build_exe_name(&self.context)?);
+        let client_wasm_path = build_wasm_path(&self.context)?;
+        let bindgen_wasm_path = self
+            .context
+            .wasm_bindgen_dir
+            .join(format!("{}_bg.wasm", self.context.output_name()?));
 
-            fs::copy(
-                exe_path,
-                server_out_dir.join(cargo_build_exe_name(&self.context)?),
+        let exe_path = if !client {
+            Some(
+                events
+                    .cancellable(ssr::cargo_build_server(&self.context))
+                    .await?,
             )
-            .await?;
+        } else if !server {
+            events
+                .cancellable(ssr::cargo_build_client(&self.context))
+                .await?;
+            None
+        } else {
+            Some(events.cancellable(ssr::cargo_build(&self.context)).await?)
+        };
 
-            self.run_ssr_exe();
+        let client_changed = client && is_newer(&client_wasm_path, &bindgen_wasm_path).await;
+        let exe_path = match exe_path {
+            Some(exe_path) if is_newer(&exe_path, &server_exe_path).await => Some(exe_path),
+            _ => None,
+        };
+        match exe_path {
+            Some(_) if client => {
+                clear_dir(&assets_dir).await?;
+                wasm_bindgen(&self.context, None, &assets_dir).await?;
+            }
+            // The collected assets of the server build are kept.
+            _ if client_changed => wasm_bindgen(&self.context, None, &assets_dir).await?,
+            _ => {}
         }
-        // When no page is open, this send will report an error.
-        let _ = self.page_tx.as_ref().unwrap().send(());
-        Ok(())
+        let Some(exe_path) = exe_path else {
+            return Ok(client_changed);
+        };
+
+        let assets = collect_assets(&self.context, Some(exe_path.clone()), &assets_dir).await?;
+        self.watch_assets(assets)?;
+
+        self.abort_ssr_exe().await?;
+        fs::copy(exe_path, server_exe_path).await?;
+        self.run_ssr_exe();
+        Ok(true)
     }
 
     fn run_ssr_exe(&mut self) {
@@ -197,6 +372,16 @@ impl DevServer {
     }
 }
 
+/// Whether `output`, made from the cargo artifact `source`, is missing or older than it.
+/// Fresh artifacts keep their modification time, so they are older than the output.
+async fn is_newer(source: &Path, output: &Path) -> bool {
+    let modified = |path| async move { fs::metadata(path).await.and_then(|m| m.modified()).ok() };
+    match (modified(source).await, modified(output).await) {
+        (Some(source), Some(output)) => source > output,
+        _ => true,
+    }
+}
+
 fn run_ssr_exe(context: Arc<Context>) -> color_eyre::Result<Child> {
     let exe_path = context
         .out_dir
@@ -219,21 +404,40 @@ fn run_ssr_exe(context: Arc<Context>) -> color_eyre::Result<Child> {
 
 pub fn default_dev_env(context: &Context) -> color_eyre::Result<Vec<(&'static str, String)>> {
     Ok(vec![
-        ("LEPTOS_OUTPUT_NAME", context.cargo_package_name()?),
-        ("LEPTOS_SITE_PKG_DIR", "assets".to_string()),
+        ("LEPTOS_OUTPUT_NAME", context.output_name()?),
+        (
+            "LEPTOS_SITE_PKG_DIR",
+            context.config.build.assets_dir.clone(),
+        ),
         ("LEPTOS_WATCH", String::new()),
         (
             "LEPTOS_RELOAD_EXTERNAL_PORT",
             context.config.server.port.to_string(),
         ),
-        ("LEPTOS_SITE_ADDR", "127.0.0.1:3000".to_string()),
+        ("LEPTOS_SITE_ADDR", context.config.server.site_addr.clone()),
+        (
+            "LEPTOS_RELOAD_WS_PROTOCOL",
+            if context.config.server.https.is_enabled() {
+                "wss"
+            } else {
+                "ws"
+            }
+            .to_string(),
+        ),
     ])
 }
 
 pub fn default_env(context: &Context) -> color_eyre::Result<Vec<(&'static str, String)>> {
-    Ok(vec![
-        ("LEPTOS_OUTPUT_NAME", context.cargo_package_name()?),
-        ("LEPTOS_SITE_PKG_DIR", "assets".to_string()),
-        ("LEPTOS_SITE_ADDR", "127.0.0.1:3000".to_string()),
-    ])
+    let mut envs = vec![
+        ("LEPTOS_OUTPUT_NAME", context.output_name()?),
+        (
+            "LEPTOS_SITE_PKG_DIR",
+            context.config.build.assets_dir.clone(),
+        ),
+        ("LEPTOS_SITE_ADDR", context.config.server.site_addr.clone()),
+    ];
+    if is_hash_filenames(context) {
+        envs.push(("LEPTOS_HASH_FILES", "true".to_string()));
+    }
+    Ok(envs)
 }
@@ -1,15 +1,28 @@
-use super::{middlewares, open_browser::open_browser, ws::handle_thaw_cli_ws};
-use crate::context::Context;
+use super::{
+    middlewares,
+    open_browser::open_browser,
+    ws::{ThawCliWs, WsMessage, thaw_cli_ws},
+};
+use crate::{
+    constants::{CLIENT_PUBLIC_PATH, THAW_CLI_WS_PATH},
+    context::Context,
+    plugins::{client_script_tag, html::apply_html_transform},
+};
 use axum::{
     Router,
-    body::Body,
-    extract::{Request, State, WebSocketUpgrade},
-    http::uri::Uri,
+    body::{Body, Bytes},
+    extract::{FromRef, Request, State, WebSocketUpgrade, ws},
+    http::{
+        header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE},
+        uri::Uri,
+    },
     response::{IntoResponse, Response},
     routing::get,
 };
+use futures_util::{StreamExt, stream};
 use hyper::{Method, StatusCode};
 use hyper_util::{client::legacy::connect::HttpConnector, rt::TokioExecutor};
+use regex::bytes::Regex;
 use std::{path::PathBuf, sync::Arc};
 use tokio::{fs, net::TcpListener, sync::broadcast};
 use tower::ServiceExt;
@@ -19,7 +32,7 @@ type Client = hyper_util::client::legacy::Client<HttpConnector, Body>;
 
 #[derive(Debug, Clone)]
 pub struct AppState {
-    tx: broadcast::Sender<()>,
+    tx: broadcast::Sender<WsMessage>,
     public_dir: PathBuf,
     public_file_service: Option<ServeDir>,
     static_file_service: ServeDir,
@@ -28,11 +41,32 @@ pub struct AppState {
     client: Client,
 }
 
-async fn cargo_leptos_ws(ws: WebSocketUpgrade, State(state): State<AppState>) -> Response {
-    ws.on_upgrade(move |socket| handle_thaw_cli_ws(socket, state.tx.clone(), true))
+impl FromRef<AppState> for ThawCliWs {
+    fn from_ref(state: &AppState) -> Self {
+        ThawCliWs::new(state.tx.clone(), None)
+    }
+}
+
+/// The reload socket of Leptos' `<AutoReload/>` is only kept open. The injected client
+/// already reloads the page, a second reload from this socket would interrupt it.
+async fn cargo_leptos_ws(ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(|mut socket| async move {
+        if socket.send(ws::Message::Ping(Bytes::new())).await.is_err() {
+            return;
+        }
+        while let Some(Ok(message)) = socket.recv().await {
+            if let ws::Message::Close(_) = message {
+                break;
+            }
+        }
+    })
 }
 
-pub async fn run(context: Arc<Context>, tx: broadcast::Sender<()>) -> color_eyre::Result<()> {
+pub async fn run(
+    context: Arc<Context>,
+    tx: broadcast::Sender<WsMessage>,
+    listener: TcpListener,
+) -> color_eyre::Result<()> {
     let client_dir = context.out_dir.join("client");
 
     let static_file_service = ServeDir::new(&client_dir)
@@ -70,30 +104,21 @@ pub async fn run(context: Arc<Context>, tx: broadcast::Sender<()>) -> color_eyre
 
     let app = Router::new()
         .route("/live_reload", get(cargo_leptos_ws))
+        .route(THAW_CLI_WS_PATH, get(thaw_cli_ws))
+        .route(&format!("{CLIENT_PUBLIC_PATH}.js"), get(client_js))
         .fallback(handler)
-        .with_state(state)
+        .with_state(state);
+    let app = context
+        .plugins
+        .configure_server(&context, app)
         .layer(middlewares::ProxyLayer::new(&context))
         .layer(CompressionLayer::new());
 
-    let addr = format!(
-        "{}:{}",
-        context.config.server.host, context.config.server.port
-    );
-
-    let listener = match TcpListener::bind(addr).await {
-        Ok(listener) => listener,
-        Err(err) => return color_eyre::Result::Err(err.into()),
-    };
-
     if context.open {
-        let url = format!(
-            "http://{}:{}",
-            context.config.server.host, context.config.server.port,
-        );
-        open_browser(&context, url)?;
+        open_browser(&context, super::local_url(&context))?;
     }
 
-    axum::serve(listener, app).await?;
+    super::serve(&context, listener, app).await?;
 
     Ok(())
 }
@@ -139,8 +164,103 @@ async fn proxy_to_backend(state: AppState, mut request: Request) -> Response {
             return (StatusCode::BAD_REQUEST, "Invalid backend URL").into_response();
         }
     };
+    // Encoded pages cannot have the client injected, the responses are compressed again
+    // by the dev server.
+    request.headers_mut().remove(ACCEPT_ENCODING);
     match state.client.request(request).await {
-        Ok(response) => response.into_response(),
+        Ok(response) => inject_client_script(response.map(Body::new)),
         Err(_) => (StatusCode::BAD_GATEWAY, "Backend service unavailable").into_response(),
     }
 }
+
+async fn client_js() -> Response {
+    (
+        [(CONTENT_TYPE, "text/javascript")],
+        include_str!("../client/client.js"),
+    )
+        .into_response()
+}
+
+/// Injects the Thaw CLI client into HTML pages rendered by the backend,
+/// so that build errors can be displayed in the browser. Only the start of the page
+/// is buffered, so that streamed responses keep streaming.
+fn inject_client_script(response: Response) -> Response {
+    let is_html = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/html"));
+    if !is_html || response.headers().contains_key(CONTENT_ENCODING) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    parts.headers.remove(CONTENT_LENGTH);
+    let body = stream::unfold(
+        (body.into_data_stream(), Some(Vec::new())),
+        |(mut body, mut prefix)| async move {
+            let Some(buffer) = &mut prefix else {
+                let chunk = body.next().await?;
+                return Some((chunk, (body, None)));
+            };
+            loop {
+                let html = match body.next().await {
+                    Some(Ok(chunk)) => {
+                        buffer.extend_from_slice(&chunk);
+                        let complete = buffer.len() > MAX_HTML_PREFIX_LEN;
+                        match inject_into_prefix(buffer, complete) {
+                            Some(html) => html,
+                            None => continue,
+                        }
+                    }
+                    Some(Err(err)) => return Some((Err(err), (body, None))),
+                    None if buffer.is_empty() => return None,
+                    None => inject_into_prefix(buffer, true)?,
+                };
+                return Some((Ok(Bytes::from(html)), (body, None)));
+            }
+        },
+    );
+
+    Response::from_parts(parts, Body::from_stream(body))
+}
+
+/// How much of a page is buffered while waiting for the opening `<head>` tag.
+const MAX_HTML_PREFIX_LEN: usize = 64 * 1024;
+
+/// Adds the client script to the start of a page once its opening `<head>` tag has arrived.
+/// With `complete`, the script is added to `prefix` without waiting for the tag.
+fn inject_into_prefix(prefix: &[u8], complete: bool) -> Option<Vec<u8>> {
+    let head_re = Regex::new(r"<head(\s[^>]*)?>").unwrap();
+    let end = match head_re.find(prefix) {
+        Some(head) => head.end(),
+        // Splits after a tag, so that no UTF-8 character is cut in half.
+        None if complete => prefix
+            .iter()
+            .rposition(|byte| *byte == b'>')
+            .map_or(0, |index| index + 1),
+        None => return None,
+    };
+    let html = apply_html_transform(
+        String::from_utf8_lossy(&prefix[..end]).into_owned(),
+        vec![client_script_tag()],
+    );
+    let mut html = html.into_bytes();
+    html.extend_from_slice(&prefix[end..]);
+    Some(html)
+}
+
+#[test]
+fn test_inject_into_prefix() {
+    assert_eq!(inject_into_prefix(b"<html><he", false), None);
+    assert_eq!(inject_into_prefix(b"<html><header>", false), None);
+
+    let html = inject_into_prefix(b"<html><head><title>", false).unwrap();
+    let html = String::from_utf8(html).unwrap();
+    assert!(html.starts_with("<html><head>\n"));
+    assert!(html.contains("<script"));
+    assert!(html.ends_with("</script>\n<title>"));
+
+    let html = inject_into_prefix(b"<p>text</p>", true).unwrap();
+    assert!(String::from_utf8(html).unwrap().starts_with("<script"));
+}
@@ -0,0 +1,90 @@
+use super::{is_wildcard_host, network_ips};
+use crate::{config::Https, context::Context, utils::thaw_cli_home_dir};
+use rcgen::CertifiedKey;
+use std::{path::PathBuf, sync::Arc};
+use tokio::fs;
+use tokio_rustls::{
+    TlsAcceptor,
+    rustls::{
+        ServerConfig,
+        pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+    },
+};
+
+/// Returns a TLS acceptor when `server.https` is enabled.
+pub async fn tls_acceptor(context: &Context) -> color_eyre::Result<Option<TlsAcceptor>> {
+    let (cert_path, key_path) = match &context.config.server.https {
+        Https::False => return Ok(None),
+        Https::SelfSigned => self_signed_cert(&context.config.server.host).await?,
+        Https::Pem { cert, key } => (
+            context.current_dir.join(cert),
+            context.current_dir.join(key),
+        ),
+    };
+
+    let certs = CertificateDer::pem_file_iter(cert_path)?.collect::<Result<Vec<_>, _>>()?;
+    let key = PrivateKeyDer::from_pem_file(key_path)?;
+
+    let mut config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    Ok(Some(TlsAcceptor::from(Arc::new(config))))
+}
+
+/// Generates a self-signed certificate for the host, cached under the Thaw CLI home directory.
+async fn self_signed_cert(host: &str) -> color_eyre::Result<(PathBuf, PathBuf)> {
+    let dir = thaw_cli_home_dir().join("certs").join(cert_dir_name(host));
+    let cert_path = dir.join("cert.pem");
+    let key_path = dir.join("key.pem");
+    let names_path = dir.join("names.txt");
+
+    let mut subject_alt_names = vec![
+        "localhost".to_string(),
+        "127.0.0.1".to_string(),
+        "::1".to_string(),
+    ];
+    if is_wildcard_host(host) {
+        // Other devices on the LAN connect through the interface IPs.
+        subject_alt_names.extend(network_ips().iter().map(ToString::to_string));
+    } else if !subject_alt_names.iter().any(|name| name == host) {
+        subject_alt_names.push(host.to_string());
+    }
+    let names = subject_alt_names.join("\n");
+
+    // The cached certificate is reused while the interface IPs stay the same.
+    if fs::try_exists(&cert_path).await?
+        && fs::try_exists(&key_path).await?
+        && fs::read_to_string(&names_path).await.ok() == Some(names.clone())
+    {
+        return Ok((cert_path, key_path));
+    }
+
+    let CertifiedKey { cert, key_pair } = rcgen::generate_simple_self_signed(subject_alt_names)?;
+
+    fs::create_dir_all(&dir).await?;
+    fs::write(&cert_path, cert.pem()).await?;
+    fs::write(&key_path, key_pair.serialize_pem()).await?;
+    fs::write(&names_path, names).await?;
+    Ok((cert_path, key_path))
+}
+
+fn cert_dir_name(host: &str) -> String {
+    host.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn test_cert_dir_name() {
+    assert_eq!(cert_dir_name("localhost"), "localhost");
+    assert_eq!(cert_dir_name("::1"), "__1");
+    assert_eq!(cert_dir_name("../etc"), ".._etc");
+}
@@ -1,33 +1,45 @@
+use super::Event;
+use crate::build::{CargoBuildError, hot_patch::HotPatch};
 use axum::{
-    body::Bytes,
     extract::{
         State, WebSocketUpgrade,
         ws::{self, WebSocket},
     },
     response::Response,
 };
+use cargo_metadata::diagnostic::Diagnostic;
 use futures_util::{SinkExt, StreamExt};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
-use tokio::{sync::broadcast, task};
+use tokio::{
+    sync::{broadcast, mpsc},
+    task,
+};
 
 #[derive(Debug, Clone)]
 pub struct ThawCliWs {
-    tx: broadcast::Sender<()>,
+    tx: broadcast::Sender<WsMessage>,
+    event_tx: Option<mpsc::Sender<Event>>,
 }
 
 impl ThawCliWs {
-    pub fn new(tx: broadcast::Sender<()>) -> Self {
-        Self { tx }
+    pub fn new(tx: broadcast::Sender<WsMessage>, event_tx: Option<mpsc::Sender<Event>>) -> Self {
+        Self { tx, event_tx }
     }
 }
 
 pub async fn thaw_cli_ws(ws: WebSocketUpgrade, State(state): State<ThawCliWs>) -> Response {
     ws.protocols(vec!["thaw-cli-ping"])
-        .on_upgrade(move |socket| handle_thaw_cli_ws(socket, state.tx.clone(), false))
+        .on_upgrade(move |socket| {
+            handle_thaw_cli_ws(socket, state.tx.clone(), state.event_tx.clone())
+        })
 }
 
-pub async fn handle_thaw_cli_ws(socket: WebSocket, tx: broadcast::Sender<()>, cargo_leptos: bool) {
+pub async fn handle_thaw_cli_ws(
+    socket: WebSocket,
+    tx: broadcast::Sender<WsMessage>,
+    event_tx: Option<mpsc::Sender<Event>>,
+) {
     if let Some(protocol) = socket.protocol()
         && let Ok(protocol) = protocol.to_str()
         && protocol == "thaw-cli-ping"
@@ -39,15 +51,24 @@ pub async fn handle_thaw_cli_ws(socket: WebSocket, tx: broadcast::Sender<()>, ca
 
     let mut recv_task = task::spawn(async move {
         while let Some(Ok(msg)) = receiver.next().await {
-            if matches!(msg, ws::Message::Close(_)) {
-                break;
+            match msg {
+                ws::Message::Close(_) => break,
+                ws::Message::Text(text) => {
+                    if let Some(event_tx) = &event_tx
+                        && let Ok(ClientMessage::HotPatchFailed { error }) =
+                            serde_json::from_str(&text)
+                    {
+                        let _ = event_tx.send(Event::HotPatchFailed(error)).await;
+                    }
+                }
+                _ => {}
             }
         }
     });
     let mut send_task = task::spawn(async move {
-        let _ = sender.send(WsMessage::Connected.into(cargo_leptos)).await;
-        while (rx.recv().await).is_ok() {
-            let _ = sender.send(WsMessage::RefreshPage.into(cargo_leptos)).await;
+        let _ = sender.send(WsMessage::Connected.into()).await;
+        while let Ok(message) = rx.recv().await {
+            let _ = sender.send(message.into()).await;
         }
     });
 
@@ -61,23 +82,131 @@ pub async fn handle_thaw_cli_ws(socket: WebSocket, tx: broadcast::Sender<()>, ca
     }
 }
 
-#[derive(Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(tag = "type")]
-enum WsMessage {
+pub enum WsMessage {
     Connected,
     RefreshPage,
+    /// Stylesheets were rebuilt, the client swaps them without a page reload.
+    AssetUpdate {
+        /// URL paths of the updated bundled stylesheets.
+        paths: Vec<String>,
+    },
+    /// The rebuild failed and the client should display an error overlay.
+    BuildError {
+        errors: Vec<BuildErrorMessage>,
+    },
+    /// Rust code was hot-patched, the client loads the patch into the running app.
+    HotPatch(HotPatch),
+}
+
+/// Sent by the client.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+enum ClientMessage {
+    /// The client could not apply a hot patch, a full rebuild is needed.
+    HotPatchFailed { error: String },
 }
 
 impl WsMessage {
-    fn into(self, cargo_leptos: bool) -> ws::Message {
-        if cargo_leptos {
-            match self {
-                WsMessage::Connected => ws::Message::Ping(Bytes::new()),
-                WsMessage::RefreshPage => ws::Message::text(r#"{"all":"reload"}"#),
+    pub fn build_error(err: &color_eyre::Report) -> Self {
+        let errors = match err.downcast_ref::<CargoBuildError>() {
+            Some(CargoBuildError { diagnostics }) if !diagnostics.is_empty() => {
+                diagnostics.iter().map(Into::into).collect()
             }
-        } else {
-            let value = serde_json::to_string(&self).unwrap();
-            ws::Message::text(value)
+            _ => vec![BuildErrorMessage {
+                message: err.to_string(),
+                file: None,
+                line: None,
+                column: None,
+                html: ansi_to_html(&format!("{err:?}")),
+            }],
+        };
+        Self::BuildError { errors }
+    }
+
+    fn into(self) -> ws::Message {
+        let value = serde_json::to_string(&self).unwrap();
+        ws::Message::text(value)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildErrorMessage {
+    pub message: String,
+    pub file: Option<String>,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    /// The rendered diagnostic, with ANSI colors converted to HTML.
+    pub html: String,
+}
+
+impl From<&Diagnostic> for BuildErrorMessage {
+    fn from(diagnostic: &Diagnostic) -> Self {
+        let span = diagnostic
+            .spans
+            .iter()
+            .find(|span| span.is_primary)
+            .or(diagnostic.spans.first());
+        let rendered = diagnostic.rendered.as_ref().unwrap_or(&diagnostic.message);
+
+        Self {
+            message: diagnostic.message.clone(),
+            file: span.map(|span| span.file_name.clone()),
+            line: span.map(|span| span.line_start),
+            column: span.map(|span| span.column_start),
+            html: ansi_to_html(rendered),
         }
     }
 }
+
+fn ansi_to_html(value: &str) -> String {
+    ansi_to_html::convert(value).unwrap_or_else(|_| {
+        value
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    })
+}
+
+#[test]
+fn test_build_error_message() {
+    let diagnostic = serde_json::from_str(
+        r#"{
+            "message": "cannot find value `x` in this scope",
+            "code": null,
+            "level": "error",
+            "spans": [{
+                "file_name": "src/app.rs",
+                "byte_start": 0,
+                "byte_end": 1,
+                "line_start": 12,
+                "line_end": 12,
+                "column_start": 5,
+                "column_end": 6,
+                "is_primary": true,
+                "text": [],
+                "label": null,
+                "suggested_replacement": null,
+                "suggestion_applicability": null,
+                "expansion": null
+            }],
+            "children": [],
+            "rendered": "\u001b[1m\u001b[38;5;9merror\u001b[0m: <x>"
+        }"#,
+    )
+    .unwrap();
+    let report = color_eyre::Report::from(CargoBuildError {
+        diagnostics: vec![diagnostic],
+    });
+
+    let WsMessage::BuildError { errors } = WsMessage::build_error(&report) else {
+        unreachable!()
+    };
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].file.as_deref(), Some("src/app.rs"));
+    assert_eq!(errors[0].line, Some(12));
+    assert_eq!(errors[0].column, Some(5));
+    assert!(!errors[0].html.contains('\u{1b}'));
+    assert!(errors[0].html.contains("&lt;x&gt;"));
+}
@@ -1,7 +1,7 @@
 pub mod client;
 mod dot_eyre;
 pub mod fs;
-mod wasm_opt;
+pub mod wasm_opt;
 
 pub use dot_eyre::DotEyre;
 pub use wasm_opt::wasm_opt_bin_path;
@@ -1,36 +1,104 @@
 use super::{fs::copy_dir_all, thaw_cli_cache_dir, thaw_cli_home_dir};
+use crate::config::WasmOptConfig;
 use color_eyre::eyre::eyre;
 use flate2::read;
-use std::path::PathBuf;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
 use tokio::fs;
 
-pub async fn wasm_opt_bin_path() -> color_eyre::Result<PathBuf> {
-    let existing_path = which::which("wasm-opt");
+/// The binaryen release installed when `build.wasm_opt.version` is not set.
+pub const DEFAULT_BINARYEN_VERSION: &str = "version_123";
 
-    if let Ok(path) = existing_path {
-        color_eyre::Result::Ok(path)
-    } else {
-        let install_dir = binaryen_install_dir().await?;
-        let install_path = install_dir.join("bin").join(binaryen_bin_name());
-        if !install_path.exists() {
-            let cache_dir = thaw_cli_cache_dir();
-            let (dir_name, url) = find_latest_binaryen_download_url().await?;
-            let bytes = reqwest::get(url).await?.bytes().await?;
-            let mut archive = tar::Archive::new(read::GzDecoder::new(bytes.as_ref()));
-            archive.unpack(cache_dir.clone())?;
-
-            let dir_path = cache_dir.join(dir_name);
-            copy_dir_all(dir_path.clone(), install_dir).await?;
-            fs::remove_dir_all(dir_path).await?;
-        }
-        color_eyre::Result::Ok(install_path)
+/// SHA-256 of the release archives of [`DEFAULT_BINARYEN_VERSION`], by archive name.
+/// These are trusted over a `.sha256` file downloaded from the same release.
+const DEFAULT_BINARYEN_SHA256: &[(&str, &str)] = &[];
+
+const BINARYEN_RELEASES_URL: &str = "https://github.com/WebAssembly/binaryen/releases/download";
+
+pub async fn wasm_opt_bin_path(config: &WasmOptConfig) -> color_eyre::Result<PathBuf> {
+    // A pinned version is always installed, wasm-opt on PATH may be any version.
+    if config.version.is_none()
+        && let Ok(path) = which::which("wasm-opt")
+    {
+        return Ok(path);
+    }
+    install_binaryen(config).await
+}
+
+/// Installs the configured binaryen release, if missing, and returns the path of wasm-opt.
+pub async fn install_binaryen(config: &WasmOptConfig) -> color_eyre::Result<PathBuf> {
+    let version = binaryen_version(config);
+    let install_dir = binaryen_dir().join(version);
+    let install_path = install_dir.join("bin").join(binaryen_bin_name());
+    if install_path.exists() {
+        return Ok(install_path);
+    }
+
+    let archive_name = binaryen_archive_name(version)?;
+    let bytes = read_binaryen_archive(config, &archive_name).await?;
+    let known_sha256 = DEFAULT_BINARYEN_SHA256
+        .iter()
+        .find(|(name, _)| *name == archive_name)
+        .map(|(_, sha256)| sha256.to_string());
+    let expected = match (&config.sha256, known_sha256) {
+        (Some(sha256), _) => sha256.clone(),
+        (None, Some(sha256)) => sha256,
+        (None, None) => read_binaryen_checksum(config, &archive_name)
+            .await?
+            .ok_or_else(|| {
+                eyre!(
+                    "No SHA-256 to verify {archive_name}. Set `build.wasm_opt.sha256`, \
+                    or add {archive_name}.sha256 next to the archive."
+                )
+            })?,
+    };
+    let actual = sha256_hex(&bytes);
+    if !actual.eq_ignore_ascii_case(expected.trim()) {
+        return Err(eyre!(
+            "SHA-256 mismatch for {archive_name}: expected {expected}, got {actual}"
+        ));
     }
+
+    let unpack_dir = thaw_cli_cache_dir().join(format!("binaryen-{version}-unpack"));
+    if fs::try_exists(&unpack_dir).await? {
+        fs::remove_dir_all(&unpack_dir).await?;
+    }
+    let mut archive = tar::Archive::new(read::GzDecoder::new(bytes.as_slice()));
+    archive.unpack(&unpack_dir)?;
+
+    // Copied next to the install directory and renamed into place, so that an interrupted
+    // copy never leaves a `bin/wasm-opt` without the `lib` it links.
+    let temp_dir = binaryen_dir().join(format!(".{version}-tmp"));
+    if fs::try_exists(&temp_dir).await? {
+        fs::remove_dir_all(&temp_dir).await?;
+    }
+    copy_dir_all(unpack_dir.join(format!("binaryen-{version}")), &temp_dir).await?;
+    fs::remove_dir_all(unpack_dir).await?;
+
+    if !fs::try_exists(temp_dir.join("bin").join(binaryen_bin_name())).await? {
+        fs::remove_dir_all(&temp_dir).await?;
+        return Err(eyre!(
+            "{archive_name} does not contain bin/{}",
+            binaryen_bin_name()
+        ));
+    }
+    if fs::try_exists(&install_dir).await? {
+        fs::remove_dir_all(&install_dir).await?;
+    }
+    fs::rename(&temp_dir, &install_dir).await?;
+    Ok(install_path)
+}
+
+/// Directory of the installed binaryen releases, one per version.
+pub fn binaryen_dir() -> PathBuf {
+    thaw_cli_home_dir().join("binaryen")
 }
 
-async fn binaryen_install_dir() -> color_eyre::Result<PathBuf> {
-    let bindgen_dir = thaw_cli_home_dir().join("binaryen");
-    fs::create_dir_all(&bindgen_dir).await?;
-    color_eyre::Result::Ok(bindgen_dir)
+fn binaryen_version(config: &WasmOptConfig) -> &str {
+    config
+        .version
+        .as_deref()
+        .unwrap_or(DEFAULT_BINARYEN_VERSION)
 }
 
 fn binaryen_bin_name() -> &'static str {
@@ -41,28 +109,7 @@ fn binaryen_bin_name() -> &'static str {
     }
 }
 
-async fn find_latest_binaryen_download_url() -> color_eyre::Result<(String, String)> {
-    let client = reqwest::Client::new();
-    let response = client
-        .get("https://api.github.com/repos/WebAssembly/binaryen/releases/latest")
-        .header("User-Agent", "thaw-cli")
-        .send()
-        .await?
-        .json::<serde_json::Value>()
-        .await?;
-    if let Some(message) = response.get("message").and_then(|message| message.as_str()) {
-        return color_eyre::Result::Err(eyre!("wasm-opt installation: {message}"));
-    }
-
-    let tag_name = response
-        .get("tag_name")
-        .and_then(|tag_name| tag_name.as_str())
-        .ok_or_else(|| eyre!("Failed to parse tag_name"))?;
-    let assets = response
-        .get("assets")
-        .and_then(|assets| assets.as_array())
-        .ok_or_else(|| eyre!("Failed to parse assets"))?;
-
+fn binaryen_archive_name(version: &str) -> color_eyre::Result<String> {
     let platform = if cfg!(all(target_os = "windows", target_arch = "x86_64")) {
         "x86_64-windows"
     } else if cfg!(all(target_os = "linux", target_arch = "x86_64")) {
@@ -78,26 +125,107 @@ async fn find_latest_binaryen_download_url() -> color_eyre::Result<(String, Stri
             "Unknown platform for wasm-opt installation. Please install wasm-opt manually from https://github.com/WebAssembly/binaryen/releases and add it to your PATH."
         ));
     };
+    Ok(format!("binaryen-{version}-{platform}.tar.gz"))
+}
 
-    let asset = assets
-        .iter()
-        .find(|asset| {
-            asset
-                .get("name")
-                .and_then(|name| name.as_str())
-                .is_some_and(|name| name.contains(platform))
-        })
-        .ok_or_else(|| {
-            eyre!(
-                "No suitable wasm-opt binary found for platform: {}. Please install wasm-opt manually from https://github.com/WebAssembly/binaryen/releases and add it to your PATH.",
-                platform
+/// Where an archive file is read from: `build.wasm_opt.mirror` or the GitHub release.
+fn binaryen_source(config: &WasmOptConfig, file_name: &str) -> String {
+    match &config.mirror {
+        Some(mirror) => format!("{}/{file_name}", mirror.trim_end_matches('/')),
+        None => format!(
+            "{BINARYEN_RELEASES_URL}/{}/{file_name}",
+            binaryen_version(config)
+        ),
+    }
+}
+
+fn is_url(source: &str) -> bool {
+    source.starts_with("http://") || source.starts_with("https://")
+}
+
+async fn read_binaryen_archive(
+    config: &WasmOptConfig,
+    archive_name: &str,
+) -> color_eyre::Result<Vec<u8>> {
+    if let Some(archive) = &config.archive {
+        return fs::read(archive)
+            .await
+            .map_err(|err| eyre!("Failed to read {}: {err}", archive.display()));
+    }
+
+    let source = binaryen_source(config, archive_name);
+    if !is_url(&source) {
+        return fs::read(&source)
+            .await
+            .map_err(|err| eyre!("Failed to read {source}: {err}"));
+    }
+    download(&source).await.map_err(|err| {
+        eyre!(
+            "Failed to download {source}: {err}\n\
+            When offline, add wasm-opt to your PATH, or set `build.wasm_opt.archive` to a downloaded \
+            {archive_name}, or `build.wasm_opt.mirror` to a directory containing it."
+        )
+    })
+}
+
+/// The published `<archive>.sha256` file, if any next to local archives.
+async fn read_binaryen_checksum(
+    config: &WasmOptConfig,
+    archive_name: &str,
+) -> color_eyre::Result<Option<String>> {
+    let checksum_name = format!("{archive_name}.sha256");
+    let content = if let Some(archive) = &config.archive {
+        let mut path = archive.as_os_str().to_owned();
+        path.push(".sha256");
+        fs::read(Path::new(&path)).await.ok()
+    } else {
+        let source = binaryen_source(config, &checksum_name);
+        if is_url(&source) {
+            Some(
+                download(&source)
+                    .await
+                    .map_err(|err| eyre!("Failed to download {source}: {err}"))?,
             )
-        })?;
+        } else {
+            fs::read(&source).await.ok()
+        }
+    };
+    Ok(content.and_then(|content| parse_checksum(&String::from_utf8_lossy(&content))))
+}
+
+async fn download(url: &str) -> reqwest::Result<Vec<u8>> {
+    let response = reqwest::get(url).await?.error_for_status()?;
+    Ok(response.bytes().await?.to_vec())
+}
+
+/// `sha256sum` output: `<hex>  <file name>`.
+fn parse_checksum(content: &str) -> Option<String> {
+    content.split_whitespace().next().map(str::to_string)
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
 
-    let download_url = asset
-        .get("browser_download_url")
-        .and_then(|url| url.as_str())
-        .ok_or_else(|| eyre!("Failed to get download URL for wasm-opt"))?;
+#[test]
+fn test_binaryen_source() {
+    let mut config = WasmOptConfig::default();
+    let archive_name = "binaryen-version_123-x86_64-linux.tar.gz";
+    assert_eq!(
+        binaryen_source(&config, archive_name),
+        "https://github.com/WebAssembly/binaryen/releases/download/version_123/binaryen-version_123-x86_64-linux.tar.gz"
+    );
 
-    Ok((format!("binaryen-{tag_name}"), download_url.to_string()))
+    config.mirror = Some("/opt/mirror/".to_string());
+    assert_eq!(
+        binaryen_source(&config, archive_name),
+        "/opt/mirror/binaryen-version_123-x86_64-linux.tar.gz"
+    );
+    assert_eq!(
+        parse_checksum("abc123  binaryen-version_123-x86_64-linux.tar.gz\n"),
+        Some("abc123".to_string())
+    );
 }